@@ -0,0 +1,338 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use crate::shared::Shared;
+
+// handoffs made before any are timed, so cold caches and first page faults stay out of the numbers
+pub const WARMUP_HANDOFFS: u64 = 1000;
+// upper bounds of the histogram buckets, with one more bucket for everything slower
+const HISTOGRAM_BOUNDS_NS: [u64; 14] = [
+    250, 500, 1_000, 2_000, 5_000, 10_000, 20_000, 50_000, 100_000, 200_000, 500_000, 1_000_000,
+    2_000_000, 5_000_000,
+];
+const BAR_WIDTH: usize = 40;
+
+// how long each handoff took, from the sender starting it to the receiver running. kept in
+// shared memory, so ping and pong processes time into the same samples
+pub struct Latencies {
+    // nanoseconds. anything past four seconds is held at u32::MAX
+    samples: Shared<[u32]>,
+    recorded: Shared<usize>,
+}
+
+impl Latencies {
+    // room for `handoffs` samples and no more
+    pub fn with_capacity(handoffs: u64) -> io::Result<Latencies> {
+        Ok(Latencies {
+            samples: Shared::filled(0, handoffs as usize)?,
+            recorded: Shared::new(0)?,
+        })
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        self.samples[*self.recorded] = latency.as_nanos().min(u32::MAX as u128) as u32;
+        *self.recorded += 1;
+    }
+
+    pub fn len(&self) -> u64 {
+        *self.recorded as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self.recorded == 0
+    }
+
+    pub fn report(&self) -> LatencyReport {
+        let mut sorted = self.samples[..*self.recorded].to_vec();
+        sorted.sort_unstable();
+        // nearest rank
+        let percentile = |p: f64| -> u64 {
+            if sorted.is_empty() {
+                return 0;
+            }
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1] as u64
+        };
+
+        let mut histogram: Vec<Bucket> = HISTOGRAM_BOUNDS_NS
+            .iter()
+            .map(|&below| Some(below))
+            .chain([None])
+            .map(|below_ns| Bucket { below_ns, count: 0 })
+            .collect();
+        for &sample in &sorted {
+            let bucket = histogram
+                .iter()
+                .position(|bucket| bucket.below_ns.is_none_or(|below| (sample as u64) < below))
+                .expect("the last bucket is open ended");
+            histogram[bucket].count += 1;
+        }
+
+        let total: u64 = sorted.iter().map(|&sample| sample as u64).sum();
+        LatencyReport {
+            samples: sorted.len() as u64,
+            min_ns: sorted.first().copied().unwrap_or(0) as u64,
+            mean_ns: if sorted.is_empty() {
+                0.0
+            } else {
+                total as f64 / sorted.len() as f64
+            },
+            p50_ns: percentile(50.0),
+            p90_ns: percentile(90.0),
+            p99_ns: percentile(99.0),
+            p999_ns: percentile(99.9),
+            max_ns: sorted.last().copied().unwrap_or(0) as u64,
+            histogram,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bucket {
+    // samples below this and at or above the previous bucket's bound. none for the last bucket
+    pub below_ns: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LatencyReport {
+    pub samples: u64,
+    pub min_ns: u64,
+    pub mean_ns: f64,
+    pub p50_ns: u64,
+    pub p90_ns: u64,
+    pub p99_ns: u64,
+    pub p999_ns: u64,
+    pub max_ns: u64,
+    pub histogram: Vec<Bucket>,
+}
+
+// one bench run, as printed at the end
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub system: String,
+    // the handoff mechanism, by its --transport name
+    pub transport: String,
+    pub seed: u64,
+    // whether each turn played frames of a match or handed straight back
+    pub bare: bool,
+    pub handoffs: u64,
+    // matches finished and frames played after the warmup
+    pub matches: u32,
+    pub frames: u64,
+    pub seconds: f64,
+    pub handoffs_per_second: f64,
+    pub latency: LatencyReport,
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "bench: {} handoffs over {} on {} (seed {})",
+            self.handoffs, self.transport, self.system, self.seed
+        )?;
+        if self.bare {
+            writeln!(f, "bare handoffs, no frames played")?;
+        } else {
+            writeln!(
+                f,
+                "{} frames, {} matches finished",
+                self.frames, self.matches
+            )?;
+        }
+        writeln!(
+            f,
+            "{:.3}s, {:.0} handoffs/s",
+            self.seconds, self.handoffs_per_second
+        )?;
+        write!(f, "{}", self.latency)
+    }
+}
+
+// the same bench over several transports, a row each
+pub struct Comparison<'a>(pub &'a [BenchReport]);
+
+impl fmt::Display for Comparison<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(first) = self.0.first() else {
+            return Ok(());
+        };
+        writeln!(
+            f,
+            "bench: {} handoffs per transport on {} (seed {})",
+            first.handoffs, first.system, first.seed
+        )?;
+        if first.bare {
+            writeln!(f, "bare handoffs, no frames played")?;
+        }
+        write!(
+            f,
+            "{:<10} {:>12} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "transport", "handoffs/s", "mean", "p50", "p90", "p99", "p99.9", "max"
+        )?;
+        for report in self.0 {
+            let latency = &report.latency;
+            write!(
+                f,
+                "\n{:<10} {:>12.0} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+                report.transport,
+                report.handoffs_per_second,
+                nanos(latency.mean_ns),
+                nanos(latency.p50_ns as f64),
+                nanos(latency.p90_ns as f64),
+                nanos(latency.p99_ns as f64),
+                nanos(latency.p999_ns as f64),
+                nanos(latency.max_ns as f64)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// 850ns, 12.3µs, 4.56ms, with round numbers left round
+fn nanos(ns: f64) -> String {
+    let (value, unit, decimals) = if ns < 1_000.0 {
+        (ns, "ns", 0)
+    } else if ns < 1_000_000.0 {
+        (ns / 1_000.0, "µs", 1)
+    } else {
+        (ns / 1_000_000.0, "ms", 2)
+    };
+    if value.fract() == 0.0 {
+        format!("{}{}", value, unit)
+    } else {
+        format!("{:.*}{}", decimals, value, unit)
+    }
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "latency: min {}  mean {}  p50 {}  p90 {}  p99 {}  p99.9 {}  max {}",
+            nanos(self.min_ns as f64),
+            nanos(self.mean_ns),
+            nanos(self.p50_ns as f64),
+            nanos(self.p90_ns as f64),
+            nanos(self.p99_ns as f64),
+            nanos(self.p999_ns as f64),
+            nanos(self.max_ns as f64)
+        )?;
+
+        let widest = self
+            .histogram
+            .iter()
+            .map(|bucket| bucket.count)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let mut from = 0;
+        for bucket in &self.histogram {
+            let label = match bucket.below_ns {
+                Some(below) if from == 0 => format!("< {}", nanos(below as f64)),
+                Some(below) => format!("{}..{}", nanos(from as f64), nanos(below as f64)),
+                None => format!(">= {}", nanos(from as f64)),
+            };
+            let bar = "█".repeat((bucket.count as usize * BAR_WIDTH).div_ceil(widest as usize));
+            let share = if self.samples == 0 {
+                0.0
+            } else {
+                bucket.count as f64 * 100.0 / self.samples as f64
+            };
+            write!(
+                f,
+                "\n  {:>13} │ {:<width$} {} ({:.3}%)",
+                label,
+                bar,
+                bucket.count,
+                share,
+                width = BAR_WIDTH
+            )?;
+            from = bucket.below_ns.unwrap_or(from);
+        }
+        Ok(())
+    }
+}
+
+// kernel and machine from uname, e.g. "Linux 6.8.0 x86_64", so runs on different boxes compare
+pub fn system() -> String {
+    let mut name: libc::utsname = unsafe { std::mem::zeroed() };
+    if unsafe { libc::uname(&mut name) } != 0 {
+        return "unknown system".to_string();
+    }
+    let field = |chars: &[libc::c_char]| {
+        unsafe { CStr::from_ptr(chars.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()
+    };
+    format!(
+        "{} {} {}",
+        field(&name.sysname),
+        field(&name.release),
+        field(&name.machine)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latencies(samples_ns: &[u64]) -> Latencies {
+        let mut latencies =
+            Latencies::with_capacity(samples_ns.len() as u64).expect("a small mapping");
+        for &ns in samples_ns {
+            latencies.record(Duration::from_nanos(ns));
+        }
+        latencies
+    }
+
+    #[test]
+    fn percentiles_take_the_nearest_rank() {
+        // 10, 20, .. 1000 ns, recorded out of order
+        let samples: Vec<u64> = (1..=100).rev().map(|n| n * 10).collect();
+        let report = latencies(&samples).report();
+        assert_eq!(report.samples, 100);
+        assert_eq!(report.min_ns, 10);
+        assert_eq!(report.mean_ns, 505.0);
+        assert_eq!(report.p50_ns, 500);
+        assert_eq!(report.p90_ns, 900);
+        assert_eq!(report.p99_ns, 990);
+        assert_eq!(report.p999_ns, 1000);
+        assert_eq!(report.max_ns, 1000);
+    }
+
+    #[test]
+    fn a_single_sample_is_every_percentile() {
+        let report = latencies(&[1234]).report();
+        assert_eq!(report.samples, 1);
+        for ns in [
+            report.min_ns,
+            report.p50_ns,
+            report.p90_ns,
+            report.p99_ns,
+            report.p999_ns,
+            report.max_ns,
+        ] {
+            assert_eq!(ns, 1234);
+        }
+
+        let empty = latencies(&[]).report();
+        assert_eq!((empty.samples, empty.p50_ns, empty.max_ns), (0, 0, 0));
+    }
+
+    #[test]
+    fn a_sample_on_a_bound_goes_in_the_bucket_above_it() {
+        let report = latencies(&[0, 249, 250, 499, 500, 5_000_000, 10_000_000_000]).report();
+        let counts: Vec<u64> = report.histogram.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts.len(), HISTOGRAM_BOUNDS_NS.len() + 1);
+        assert_eq!(&counts[..3], [2, 2, 1]);
+        // the last bound and everything past four seconds share the open ended bucket
+        assert_eq!(counts[HISTOGRAM_BOUNDS_NS.len()], 2);
+        assert_eq!(counts.iter().sum::<u64>(), 7);
+        assert_eq!(report.max_ns, u32::MAX as u64);
+    }
+}
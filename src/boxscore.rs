@@ -0,0 +1,434 @@
+use serde::Serialize;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::game::{Event, GameState, PointReason, Side};
+use crate::observer::Observer;
+
+// the spin a hit puts on the ball is sorted into this many equal bins, from full backspin
+// to full topspin
+pub const SPIN_BINS: usize = 6;
+// scores per line of the progression in the text table
+const PROGRESSION_WIDTH: usize = 12;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PlayerStats {
+    pub points_won: u32,
+    pub won_on_serve: u32,
+    pub won_on_receive: u32,
+    // points started on this player's serve
+    pub served: u32,
+    pub hits: u32,
+    // points lost by fluffing a ball at the paddle, by letting one past and by a bad serve
+    pub misses: u32,
+    pub outs: u32,
+    pub faults: u32,
+    // fastest ball off this player's serve or paddle
+    pub top_speed: f64,
+    pub spin: [u32; SPIN_BINS],
+}
+
+// one point of the score progression
+#[derive(Clone, Debug, Serialize)]
+pub struct PointRecord {
+    pub point: u32,
+    pub game: u32,
+    pub server: Side,
+    pub winner: Side,
+    pub reason: &'static str,
+    // the player whose miss, out or fault decided it
+    pub by: Side,
+    pub rally: u32,
+    // score and games once the point is counted
+    pub score: [u32; 2],
+    pub games: [u32; 2],
+    pub top_speed: f64,
+}
+
+// everything that happened in one match, collected from its events
+#[derive(Clone, Debug, Serialize)]
+pub struct BoxScore {
+    pub seed: u64,
+    pub players: [PlayerStats; 2],
+    pub net_cords: u32,
+    pub lets: u32,
+    pub mean_rally: f64,
+    pub median_rally: f64,
+    pub longest_rally: u32,
+    // SPIN_BINS + 1 edges of the spin bins
+    pub spin_edges: Vec<f64>,
+    pub points: Vec<PointRecord>,
+    #[serde(skip)]
+    hit_spin: f64,
+    // who served the point being played, the game has moved on by the time it is scored
+    #[serde(skip)]
+    server: Side,
+    #[serde(skip)]
+    point_top_speed: f64,
+}
+
+impl BoxScore {
+    pub fn new(game: &GameState) -> BoxScore {
+        let hit_spin = game.physics.hit_spin;
+        BoxScore {
+            seed: game.seed,
+            players: Default::default(),
+            net_cords: 0,
+            lets: 0,
+            mean_rally: 0.0,
+            median_rally: 0.0,
+            longest_rally: 0,
+            spin_edges: (0..=SPIN_BINS)
+                .map(|edge| hit_spin * (2.0 * edge as f64 / SPIN_BINS as f64 - 1.0))
+                .collect(),
+            points: Vec::new(),
+            hit_spin,
+            server: game.serving_player,
+            point_top_speed: 0.0,
+        }
+    }
+
+    fn spin_bin(&self, spin: f64) -> usize {
+        if self.hit_spin <= 0.0 {
+            return SPIN_BINS / 2;
+        }
+        let position = (spin / self.hit_spin + 1.0) / 2.0;
+        ((position * SPIN_BINS as f64) as usize).min(SPIN_BINS - 1)
+    }
+
+    fn ball_played(&mut self, side: Side, speed: f64) {
+        let player = &mut self.players[side.index()];
+        player.top_speed = player.top_speed.max(speed);
+        self.point_top_speed = self.point_top_speed.max(speed);
+    }
+
+    fn update_rallies(&mut self) {
+        let mut rallies: Vec<u32> = self.points.iter().map(|point| point.rally).collect();
+        rallies.sort_unstable();
+        let count = rallies.len();
+        self.mean_rally = rallies.iter().sum::<u32>() as f64 / count as f64;
+        self.median_rally = if count % 2 == 1 {
+            rallies[count / 2] as f64
+        } else {
+            (rallies[count / 2 - 1] + rallies[count / 2]) as f64 / 2.0
+        };
+        self.longest_rally = rallies[count - 1];
+    }
+
+    // one row per point, with a header
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "point,game,server,winner,reason,by,rally,ping_score,pong_score,ping_games,pong_games,top_speed"
+        )?;
+        for point in &self.points {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{},{},{:.3}",
+                point.point,
+                point.game,
+                point.server,
+                point.winner,
+                point.reason,
+                point.by,
+                point.rally,
+                point.score[0],
+                point.score[1],
+                point.games[0],
+                point.games[1],
+                point.top_speed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Observer for BoxScore {
+    fn observe(&mut self, game: &GameState, event: &Event) {
+        match *event {
+            Event::Serve(side) => {
+                self.server = side;
+                self.ball_played(side, game.ball.speed());
+            }
+            Event::PaddleHit(side) => {
+                self.ball_played(side, game.ball.speed());
+                let bin = self.spin_bin(game.ball.spin);
+                let player = &mut self.players[side.index()];
+                player.hits += 1;
+                player.spin[bin] += 1;
+            }
+            Event::NetCord => self.net_cords += 1,
+            Event::Let(_) => self.lets += 1,
+            Event::PointScored { winner, reason } => {
+                let server = self.server;
+                self.players[server.index()].served += 1;
+                let player = &mut self.players[winner.index()];
+                player.points_won += 1;
+                if winner == server {
+                    player.won_on_serve += 1;
+                } else {
+                    player.won_on_receive += 1;
+                }
+
+                let loser = &mut self.players[reason.side().index()];
+                match reason {
+                    PointReason::Miss(_) => loser.misses += 1,
+                    PointReason::Out(_) => loser.outs += 1,
+                    PointReason::Fault(_) => loser.faults += 1,
+                }
+
+                // a won game has already been added to the games but not cleared from the score
+                self.points.push(PointRecord {
+                    point: self.points.len() as u32 + 1,
+                    game: game.game_number,
+                    server,
+                    winner,
+                    reason: reason.name(),
+                    by: reason.side(),
+                    rally: game.rally_length,
+                    score: game.score,
+                    games: game.games,
+                    top_speed: self.point_top_speed,
+                });
+                self.point_top_speed = 0.0;
+                self.update_rallies();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for BoxScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [ping, pong] = &self.players;
+        writeln!(f, "box score (seed {})", self.seed)?;
+        writeln!(f, "{:<24} {:>8} {:>8}", "", "ping", "pong")?;
+        let mut row = |label: &str, ping: String, pong: String| {
+            writeln!(f, "{:<24} {:>8} {:>8}", label, ping, pong)
+        };
+        row(
+            "points won",
+            ping.points_won.to_string(),
+            pong.points_won.to_string(),
+        )?;
+        row(
+            "  on own serve",
+            ping.won_on_serve.to_string(),
+            pong.won_on_serve.to_string(),
+        )?;
+        row(
+            "  on receive",
+            ping.won_on_receive.to_string(),
+            pong.won_on_receive.to_string(),
+        )?;
+        row(
+            "points served",
+            ping.served.to_string(),
+            pong.served.to_string(),
+        )?;
+        row("hits", ping.hits.to_string(), pong.hits.to_string())?;
+        row("misses", ping.misses.to_string(), pong.misses.to_string())?;
+        row(
+            "balls let past",
+            ping.outs.to_string(),
+            pong.outs.to_string(),
+        )?;
+        row(
+            "service faults",
+            ping.faults.to_string(),
+            pong.faults.to_string(),
+        )?;
+        row(
+            "top ball speed",
+            format!("{:.2}", ping.top_speed),
+            format!("{:.2}", pong.top_speed),
+        )?;
+        writeln!(f, "spin per hit (- back, + top)")?;
+        for bin in 0..SPIN_BINS {
+            let label = format!(
+                "  {:+.2} .. {:+.2}",
+                self.spin_edges[bin],
+                self.spin_edges[bin + 1]
+            );
+            writeln!(
+                f,
+                "{:<24} {:>8} {:>8}",
+                label, ping.spin[bin], pong.spin[bin]
+            )?;
+        }
+
+        writeln!(f, "net cords: {}  lets: {}", self.net_cords, self.lets)?;
+        writeln!(
+            f,
+            "rally length: mean {:.2}  median {}  longest {}",
+            self.mean_rally, self.median_rally, self.longest_rally
+        )?;
+
+        write!(f, "score progression (ping-pong):")?;
+        for game in self.points.chunk_by(|a, b| a.game == b.game) {
+            for (n, line) in game.chunks(PROGRESSION_WIDTH).enumerate() {
+                let scores: Vec<String> = line
+                    .iter()
+                    .map(|point| format!("{}-{}", point.score[0], point.score[1]))
+                    .collect();
+                if n == 0 {
+                    write!(f, "\n  game {}: {}", game[0].game, scores.join(" "))?;
+                } else {
+                    write!(f, "\n          {}", scores.join(" "))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Ball;
+
+    fn ball(speed: f64, spin: f64) -> Ball {
+        Ball {
+            x: 40.0,
+            y: 10.0,
+            dx: speed,
+            dy: 0.0,
+            spin,
+        }
+    }
+
+    // two games: ping misses, pong lets one past, pong faults, then ping misses again in game 2
+    fn scored_match() -> BoxScore {
+        let mut game = GameState::with_seed(1);
+        let mut box_score = BoxScore::new(&game);
+        let hit_spin = game.physics.hit_spin;
+        let mut feed = |game: &mut GameState, event: Event| box_score.observe(game, &event);
+
+        game.ball = ball(5.0, 0.0);
+        feed(&mut game, Event::Serve(Side::Ping));
+        game.ball = ball(6.0, hit_spin * 0.9);
+        feed(&mut game, Event::PaddleHit(Side::Pong));
+        game.rally_length = 3;
+        game.score = [0, 1];
+        feed(
+            &mut game,
+            Event::PointScored {
+                winner: Side::Pong,
+                reason: PointReason::Miss(Side::Ping),
+            },
+        );
+
+        game.ball = ball(5.0, 0.0);
+        feed(&mut game, Event::Serve(Side::Ping));
+        feed(&mut game, Event::NetCord);
+        feed(&mut game, Event::Let(Side::Ping));
+        feed(&mut game, Event::Serve(Side::Ping));
+        game.rally_length = 1;
+        game.score = [1, 1];
+        feed(
+            &mut game,
+            Event::PointScored {
+                winner: Side::Ping,
+                reason: PointReason::Out(Side::Pong),
+            },
+        );
+
+        feed(&mut game, Event::Serve(Side::Pong));
+        game.rally_length = 0;
+        game.score = [2, 1];
+        feed(
+            &mut game,
+            Event::PointScored {
+                winner: Side::Ping,
+                reason: PointReason::Fault(Side::Pong),
+            },
+        );
+
+        game.game_number = 2;
+        game.games = [1, 0];
+        feed(&mut game, Event::Serve(Side::Pong));
+        game.ball = ball(4.0, -hit_spin * 0.9);
+        feed(&mut game, Event::PaddleHit(Side::Ping));
+        game.rally_length = 2;
+        game.score = [0, 1];
+        feed(
+            &mut game,
+            Event::PointScored {
+                winner: Side::Pong,
+                reason: PointReason::Miss(Side::Ping),
+            },
+        );
+        box_score
+    }
+
+    #[test]
+    fn points_are_tallied_by_winner_server_and_reason() {
+        let box_score = scored_match();
+        let [ping, pong] = &box_score.players;
+        assert_eq!(
+            (ping.points_won, ping.won_on_serve, ping.won_on_receive),
+            (2, 1, 1)
+        );
+        assert_eq!(
+            (pong.points_won, pong.won_on_serve, pong.won_on_receive),
+            (2, 1, 1)
+        );
+        assert_eq!((ping.served, pong.served), (2, 2));
+        assert_eq!((ping.misses, ping.outs, ping.faults), (2, 0, 0));
+        assert_eq!((pong.misses, pong.outs, pong.faults), (0, 1, 1));
+        assert_eq!((ping.hits, pong.hits), (1, 1));
+        assert_eq!(ping.spin, [1, 0, 0, 0, 0, 0]);
+        assert_eq!(pong.spin, [0, 0, 0, 0, 0, 1]);
+        assert_eq!((ping.top_speed, pong.top_speed), (5.0, 6.0));
+        assert_eq!((box_score.net_cords, box_score.lets), (1, 1));
+    }
+
+    #[test]
+    fn rallies_and_points_are_kept_per_game() {
+        let box_score = scored_match();
+        assert_eq!(box_score.longest_rally, 3);
+        assert_eq!(box_score.mean_rally, 1.5);
+        assert_eq!(box_score.median_rally, 1.5);
+
+        let games: Vec<u32> = box_score.points.iter().map(|point| point.game).collect();
+        assert_eq!(games, [1, 1, 1, 2]);
+        let last = box_score.points.last().expect("four points were scored");
+        assert_eq!(
+            (last.point, last.server, last.by),
+            (4, Side::Pong, Side::Ping)
+        );
+        assert_eq!((last.score, last.games), ([0, 1], [1, 0]));
+        assert_eq!(last.top_speed, 5.0);
+    }
+
+    #[test]
+    fn the_table_and_the_csv_show_the_tallies() {
+        let box_score = scored_match();
+        let table = box_score.to_string();
+        for line in [
+            format!("{:<24} {:>8} {:>8}", "points won", 2, 2),
+            format!("{:<24} {:>8} {:>8}", "service faults", 0, 1),
+            format!("{:<24} {:>8} {:>8}", "top ball speed", "5.00", "6.00"),
+            "net cords: 1  lets: 1".to_string(),
+            "rally length: mean 1.50  median 1.5  longest 3".to_string(),
+            "  game 1: 0-1 1-1 2-1".to_string(),
+            "  game 2: 0-1".to_string(),
+        ] {
+            assert!(
+                table.lines().any(|row| row == line),
+                "{:?} in\n{}",
+                line,
+                table
+            );
+        }
+
+        let mut csv = Vec::new();
+        box_score
+            .write_csv(&mut csv)
+            .expect("a Vec takes every write");
+        let csv = String::from_utf8(csv).expect("csv is utf-8");
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[3], "3,1,pong,ping,fault,pong,0,2,1,0,0,5.000");
+    }
+}
@@ -0,0 +1,593 @@
+use sig_ping_pong::controller::{CONTROLLER_HELP, ControllerKind};
+use sig_ping_pong::handoff::Transport;
+use sig_ping_pong::skill::{SKILLS, Skill};
+use sig_ping_pong::{Court, Side};
+
+const PROGRAM: &str = "sig-ping-pong";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Command {
+    // an animated match in the terminal, the default without a command
+    #[default]
+    Play,
+    // headless matches with a statistics report
+    Simulate,
+    // a recorded match played back
+    Replay,
+    // headless matches through the real handoffs, timed
+    Bench,
+    // bare handoffs between two threads, checked turn by turn
+    Stress,
+}
+
+const COMMANDS: [Command; 5] = [
+    Command::Play,
+    Command::Simulate,
+    Command::Replay,
+    Command::Bench,
+    Command::Stress,
+];
+
+impl Command {
+    fn from_name(name: &str) -> Option<Command> {
+        COMMANDS.into_iter().find(|command| command.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Play => "play",
+            Command::Simulate => "simulate",
+            Command::Replay => "replay",
+            Command::Bench => "bench",
+            Command::Stress => "stress",
+        }
+    }
+
+    fn summary(self) -> &'static str {
+        match self {
+            Command::Play => "play an animated match in the terminal",
+            Command::Simulate => "play matches headless and report statistics",
+            Command::Replay => "watch a match recorded with play --record",
+            Command::Bench => {
+                "time the handoffs between ping and pong, without rendering or delays"
+            }
+            Command::Stress => {
+                "play a match between two threads under stray signals and check every turn against the game"
+            }
+        }
+    }
+
+    fn arguments(self) -> &'static str {
+        match self {
+            Command::Replay => " [OPTIONS] FILE",
+            _ => " [OPTIONS]",
+        }
+    }
+
+    // the flags this command takes besides --help and --print-config
+    fn flags(self) -> &'static [&'static str] {
+        match self {
+            Command::Play => &[
+                "--seed",
+                "--max-score",
+                "--best-of",
+                "--ping",
+                "--pong",
+                "--human",
+                "--skill",
+                "--ping-skill",
+                "--pong-skill",
+                "--court",
+                "--frame-delay",
+                "--transport",
+                "--config",
+                "--format",
+                "--events",
+                "--stats-json",
+                "--stats-csv",
+                "--record",
+                "--render-stats",
+            ],
+            Command::Simulate => &[
+                "--matches",
+                "--seed",
+                "--max-score",
+                "--best-of",
+                "--ping",
+                "--pong",
+                "--skill",
+                "--ping-skill",
+                "--pong-skill",
+                "--court",
+                "--config",
+                "--format",
+                "--events",
+            ],
+            Command::Bench => &[
+                "--handoffs",
+                "--bare",
+                "--transport",
+                "--seed",
+                "--max-score",
+                "--best-of",
+                "--ping",
+                "--pong",
+                "--skill",
+                "--ping-skill",
+                "--pong-skill",
+                "--court",
+                "--config",
+                "--format",
+            ],
+            Command::Stress => &[
+                "--handoffs",
+                "--transport",
+                "--process-wide",
+                "--seed",
+                "--format",
+            ],
+            Command::Replay => &["--config"],
+        }
+    }
+}
+
+// flag, its value and what it does, in the order --help lists them
+const FLAGS: &[(&str, &str, &str)] = &[
+    (
+        "--matches",
+        "N",
+        "matches to play, each on its own seed mixed from --seed (default 100)",
+    ),
+    (
+        "--handoffs",
+        "N",
+        "handoffs to make, bench times them after a warmup of 1000 (default 1000000)",
+    ),
+    (
+        "--bare",
+        "",
+        "hand each turn straight back without playing, to time the handoff alone",
+    ),
+    (
+        "--transport",
+        "NAME",
+        "how turns are handed over: signal (default), classic (x86_64 only), tgkill, eventfd, pipe, futex, condvar or process. bench and stress take all, stress only the threaded ones and tgkill by default",
+    ),
+    (
+        "--process-wide",
+        "",
+        "hand over the way flip and flop do, SIGUSR1 to the whole process, to see turns go wrong",
+    ),
+    ("--seed", "N", "seed for the match, random by default"),
+    ("--max-score", "POINTS", "points needed to win a game"),
+    ("--best-of", "GAMES", "games in the match, an odd number"),
+    ("--ping", "PLAYER", "who plays ping"),
+    ("--pong", "PLAYER", "who plays pong"),
+    (
+        "--human",
+        "ping|pong|both",
+        "shorthand for --ping human and/or --pong human",
+    ),
+    ("--skill", "SKILL", "skill of both players"),
+    ("--ping-skill", "SKILL", "skill of ping"),
+    ("--pong-skill", "SKILL", "skill of pong"),
+    (
+        "--court",
+        "WIDTHxHEIGHT",
+        "court size, play fills the terminal by default",
+    ),
+    (
+        "--frame-delay",
+        "MS",
+        "milliseconds each frame stays on screen",
+    ),
+    (
+        "--config",
+        "FILE",
+        "physics, rules, timing and skills from a toml file",
+    ),
+    ("--format", "text|json", "how the results are printed"),
+    (
+        "--events",
+        "FILE",
+        "write every game event to FILE as json lines",
+    ),
+    (
+        "--stats-json",
+        "FILE",
+        "write the box score of the last match to FILE as json",
+    ),
+    (
+        "--stats-csv",
+        "FILE",
+        "write the last match's score progression to FILE as csv",
+    ),
+    ("--record", "FILE", "record the match for replay"),
+    (
+        "--render-stats",
+        "",
+        "show the renderer's byte counts under the board",
+    ),
+    (
+        "--print-config",
+        "",
+        "print every setting with its built-in value and exit",
+    ),
+    ("--help", "", "print this help and exit"),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Default)]
+pub struct Options {
+    pub command: Command,
+    pub matches: Option<u32>,
+    pub handoffs: Option<u64>,
+    pub bare: bool,
+    // none for the command's default
+    pub transport: Option<Transport>,
+    // --transport all
+    pub all_transports: bool,
+    pub process_wide: bool,
+    pub seed: Option<u64>,
+    pub max_score: Option<u32>,
+    pub best_of: Option<u32>,
+    pub controllers: [ControllerKind; 2],
+    pub skills: [Skill; 2],
+    pub court: Option<Court>,
+    pub frame_delay: Option<u64>,
+    pub config: Option<String>,
+    pub format: Format,
+    pub events: Option<String>,
+    pub stats_json: Option<String>,
+    pub stats_csv: Option<String>,
+    pub print_config: bool,
+    pub render_stats: bool,
+    pub record: Option<String>,
+    // the file a replay plays back
+    pub file: Option<String>,
+}
+
+fn help(command: Option<Command>) -> String {
+    let mut text = String::new();
+    let flags = match command {
+        Some(command) => {
+            text.push_str(&format!(
+                "{}\n\nusage: {} {}{}\n",
+                command.summary(),
+                PROGRAM,
+                command.name(),
+                command.arguments()
+            ));
+            command.flags()
+        }
+        None => {
+            text.push_str(&format!(
+                "usage: {} [COMMAND] [OPTIONS]\n\ncommands:\n",
+                PROGRAM
+            ));
+            for command in COMMANDS {
+                text.push_str(&format!("  {:<10} {}\n", command.name(), command.summary()));
+            }
+            text.push_str(&format!(
+                "without a command the options are those of play.\nrun {} COMMAND --help for a command's options\n",
+                PROGRAM
+            ));
+            &[]
+        }
+    };
+
+    if !flags.is_empty() {
+        text.push_str("\noptions:\n");
+    }
+    for (flag, value, description) in FLAGS {
+        let global = matches!(*flag, "--help" | "--print-config");
+        if command.is_some() && (global || flags.contains(flag)) {
+            let flag = format!("{} {}", flag, value);
+            text.push_str(&format!("  {:<30} {}\n", flag.trim_end(), description));
+        }
+    }
+
+    if command.is_none_or(|command| command.flags().contains(&"--ping")) {
+        text.push_str(&format!("\nplayers: {}\n", CONTROLLER_HELP));
+        text.push_str(&format!(
+            "skills: {}\n",
+            SKILLS.map(|skill| skill.name).join(", ")
+        ));
+    }
+    if command.is_none_or(|command| command == Command::Play) {
+        text.push_str(
+            "exit status: 10 if ping won the match, 11 if pong won, 0 if it was abandoned\n",
+        );
+    }
+    text
+}
+
+pub fn usage_error(command: Command, message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!(
+        "usage: {} {}{}, see {} {} --help",
+        PROGRAM,
+        command.name(),
+        command.arguments(),
+        PROGRAM,
+        command.name()
+    );
+    std::process::exit(2);
+}
+
+// `args` without `flags` and their values, to run the program again with those replaced
+pub fn strip_flags(args: impl IntoIterator<Item = String>, flags: &[&str]) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+        if !flags.contains(&flag) {
+            kept.push(arg);
+        } else if !arg.contains('=') {
+            args.next();
+        }
+    }
+    kept
+}
+
+fn parse_value<T: std::str::FromStr>(command: Command, flag: &str, value: Option<String>) -> T {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(value)) => value,
+        _ => usage_error(command, &format!("{} expects an unsigned integer", flag)),
+    }
+}
+
+// exits with a usage error on anything it does not understand
+pub fn parse(args: impl IntoIterator<Item = String>) -> Options {
+    let mut args = args.into_iter().peekable();
+    let mut options = Options::default();
+
+    let named = args.peek().and_then(|arg| Command::from_name(arg));
+    if let Some(command) = named {
+        options.command = command;
+        args.next();
+    }
+    let command = options.command;
+
+    while let Some(arg) = args.next() {
+        if command == Command::Replay && !arg.starts_with('-') && options.file.is_none() {
+            options.file = Some(arg);
+            continue;
+        }
+
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+
+        match flag.as_str() {
+            "--help" | "-h" => {
+                print!("{}", help(named));
+                std::process::exit(0);
+            }
+            "--print-config" => {
+                options.print_config = true;
+                continue;
+            }
+            _ => {}
+        }
+        if !FLAGS.iter().any(|(known, _, _)| *known == flag) {
+            usage_error(command, &format!("unknown argument: {}", flag));
+        }
+        if !command.flags().contains(&flag.as_str()) {
+            usage_error(
+                command,
+                &format!("{} does not take {}", command.name(), flag),
+            );
+        }
+
+        let mut value = || inline.clone().or_else(|| args.next());
+        match flag.as_str() {
+            "--matches" => options.matches = Some(parse_value(command, &flag, value())),
+            "--handoffs" => {
+                let handoffs: u64 = parse_value(command, &flag, value());
+                if handoffs == 0 {
+                    usage_error(command, "--handoffs expects at least 1 handoff");
+                }
+                options.handoffs = Some(handoffs);
+            }
+            "--bare" => options.bare = true,
+            "--transport" => match value().as_deref() {
+                Some("all") if matches!(command, Command::Bench | Command::Stress) => {
+                    options.all_transports = true;
+                }
+                Some(name) if let Some(transport) = Transport::from_name(name) => {
+                    options.transport = Some(transport);
+                }
+                _ => usage_error(
+                    command,
+                    "--transport expects signal, classic, tgkill, eventfd, pipe, futex, condvar or process",
+                ),
+            },
+            "--process-wide" => options.process_wide = true,
+            "--seed" => options.seed = Some(parse_value(command, &flag, value())),
+            "--max-score" => {
+                let points: u32 = parse_value(command, &flag, value());
+                if points < 2 {
+                    usage_error(command, "--max-score expects at least 2 points");
+                }
+                options.max_score = Some(points);
+            }
+            "--best-of" => {
+                let games: u32 = parse_value(command, &flag, value());
+                if games.is_multiple_of(2) {
+                    usage_error(command, "--best-of expects an odd number of games");
+                }
+                options.best_of = Some(games);
+            }
+            "--ping" | "--pong" => {
+                let side = Side::from_name(&flag[2..]).expect("flag names a side");
+                let spec = value()
+                    .unwrap_or_else(|| usage_error(command, &format!("{} expects a player", flag)));
+                let kind = ControllerKind::parse(&spec, side)
+                    .unwrap_or_else(|err| usage_error(command, &err));
+                options.controllers[side.index()] = kind;
+            }
+            "--skill" | "--ping-skill" | "--pong-skill" => {
+                let name = value().unwrap_or_default();
+                let skill = Skill::from_name(&name)
+                    .unwrap_or_else(|| usage_error(command, &format!("unknown skill {}", name)));
+                match flag.as_str() {
+                    "--ping-skill" => options.skills[Side::Ping.index()] = skill,
+                    "--pong-skill" => options.skills[Side::Pong.index()] = skill,
+                    _ => options.skills = [skill; 2],
+                }
+            }
+            "--human" => {
+                let sides: &[Side] = match value().as_deref() {
+                    Some("ping") => &[Side::Ping],
+                    Some("pong") => &[Side::Pong],
+                    Some("both") => &[Side::Ping, Side::Pong],
+                    _ => usage_error(command, "--human expects ping, pong or both"),
+                };
+                for side in sides {
+                    options.controllers[side.index()] = ControllerKind::Human;
+                }
+            }
+            "--court" => {
+                let size = value().unwrap_or_default();
+                let court = size
+                    .parse()
+                    .unwrap_or_else(|err: String| usage_error(command, &err));
+                options.court = Some(court);
+            }
+            "--frame-delay" => options.frame_delay = Some(parse_value(command, &flag, value())),
+            "--format" => {
+                options.format = match value().as_deref() {
+                    Some("text") => Format::Text,
+                    Some("json") => Format::Json,
+                    _ => usage_error(command, "--format expects text or json"),
+                }
+            }
+            "--config" | "--events" | "--stats-json" | "--stats-csv" | "--record" => {
+                let path = value().unwrap_or_else(|| {
+                    usage_error(command, &format!("{} expects a file path", flag))
+                });
+                match flag.as_str() {
+                    "--config" => options.config = Some(path),
+                    "--events" => options.events = Some(path),
+                    "--stats-json" => options.stats_json = Some(path),
+                    "--stats-csv" => options.stats_csv = Some(path),
+                    _ => options.record = Some(path),
+                }
+            }
+            "--render-stats" => options.render_stats = true,
+            _ => unreachable!("every flag in FLAGS is handled"),
+        }
+    }
+
+    if command == Command::Replay && options.file.is_none() && !options.print_config {
+        usage_error(command, "replay expects the file to play back");
+    }
+    if matches!(command, Command::Simulate | Command::Bench)
+        && options.controllers.contains(&ControllerKind::Human)
+    {
+        usage_error(command, "human players cannot play headless");
+    }
+    if command == Command::Stress
+        && options
+            .transport
+            .is_some_and(|transport| !transport.threaded())
+    {
+        usage_error(
+            command,
+            "stress takes the threaded transports: tgkill, eventfd, pipe, futex or condvar",
+        );
+    }
+    if options.process_wide && (options.transport.is_some() || options.all_transports) {
+        usage_error(command, "--process-wide replaces --transport");
+    }
+    // each process only sees its own turns, there is no one place to follow the whole match
+    if options.transport.is_some_and(Transport::forks)
+        && (options.events.is_some()
+            || options.record.is_some()
+            || options.stats_json.is_some()
+            || options.stats_csv.is_some())
+    {
+        usage_error(
+            command,
+            "--transport process shares only the game between ping and pong, it cannot take --events, --record, --stats-json or --stats-csv",
+        );
+    }
+
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn without_a_command_the_options_are_those_of_play() {
+        let options = parse(args("--seed 7 --max-score=5 --best-of 3"));
+        assert_eq!(options.command, Command::Play);
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.max_score, Some(5));
+        assert_eq!(options.best_of, Some(3));
+    }
+
+    #[test]
+    fn a_command_takes_its_own_flags() {
+        let options = parse(args("bench --handoffs 100 --transport all --bare"));
+        assert_eq!(options.command, Command::Bench);
+        assert_eq!(options.handoffs, Some(100));
+        assert!(options.all_transports);
+        assert!(options.bare);
+
+        let options = parse(args("stress --transport futex"));
+        assert_eq!(options.transport, Some(Transport::Futex));
+
+        let options = parse(args("simulate --matches 3 --format json --court 40x12"));
+        assert_eq!(options.command, Command::Simulate);
+        assert_eq!(options.matches, Some(3));
+        assert_eq!(options.format, Format::Json);
+        assert_eq!(options.court, Court::new(40, 12).ok());
+    }
+
+    #[test]
+    fn players_and_skills_are_set_per_side() {
+        let skill = SKILLS[1];
+        let options = parse(args(&format!(
+            "--human pong --ping perfect --ping-skill {}",
+            skill.name
+        )));
+        assert_eq!(
+            options.controllers,
+            [ControllerKind::Perfect, ControllerKind::Human]
+        );
+        assert_eq!(options.skills[Side::Ping.index()], skill);
+        assert_eq!(options.skills[Side::Pong.index()], Skill::default());
+    }
+
+    #[test]
+    fn a_replay_takes_its_file_first() {
+        let options = parse(args("replay match.txt --config tuned.toml"));
+        assert_eq!(options.command, Command::Replay);
+        assert_eq!(options.file.as_deref(), Some("match.txt"));
+        assert_eq!(options.config.as_deref(), Some("tuned.toml"));
+    }
+
+    #[test]
+    fn stripped_flags_lose_their_values_too() {
+        let kept = strip_flags(
+            args("play --seed 4 --transport=pipe --record out.txt --best-of 3"),
+            &["--transport", "--record"],
+        );
+        assert_eq!(kept, args("play --seed 4 --best-of 3"));
+    }
+}
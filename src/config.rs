@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use crate::Timing;
+use crate::court::{Court, MIN_HEIGHT};
+use crate::game::{GameState, Rules};
+use crate::physics::Physics;
+use crate::skill::{SKILLS, Skill};
+
+// everything a designer can tune without recompiling. missing keys keep the built-in values
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub rules: Rules,
+    pub timing: Timing,
+    pub physics: Physics,
+    // changes to the skill profiles, keyed by profile name
+    pub skills: BTreeMap<String, SkillTuning>,
+}
+
+// fields left out keep the profile's own value
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillTuning {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paddle_speed: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_noise: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub miss_base: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difficulty_scaling: Option<f64>,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read config {}: {}", path.display(), err))?;
+        Config::parse(&text).map_err(|err| format!("config {}: {}", path.display(), err))
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(text).map_err(|err| err.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // every setting with its built-in value, as a starting point for a config file
+    pub fn defaults_toml() -> String {
+        let mut config = Config::default();
+        for skill in SKILLS {
+            config.skills.insert(
+                skill.name.to_string(),
+                SkillTuning {
+                    paddle_speed: Some(skill.paddle_speed),
+                    target_noise: Some(skill.target_noise),
+                    miss_base: Some(skill.miss_base),
+                    difficulty_scaling: Some(skill.difficulty_scaling),
+                },
+            );
+        }
+        toml::to_string(&config).expect("the config always serializes")
+    }
+
+    // `skill` with this config's changes to its profile
+    pub fn skill(&self, skill: Skill) -> Skill {
+        let Some(tuning) = self.skills.get(skill.name) else {
+            return skill;
+        };
+        Skill {
+            paddle_speed: tuning.paddle_speed.unwrap_or(skill.paddle_speed),
+            target_noise: tuning.target_noise.unwrap_or(skill.target_noise),
+            miss_base: tuning.miss_base.unwrap_or(skill.miss_base),
+            difficulty_scaling: tuning
+                .difficulty_scaling
+                .unwrap_or(skill.difficulty_scaling),
+            ..skill
+        }
+    }
+
+    // a fresh match under this config. skills are per player, so they are left to the caller
+    pub fn new_game(&self, seed: u64, court: Court) -> GameState {
+        let mut game = GameState::with_court(seed, self.rules, court);
+        game.physics = self.physics;
+        game.timing = self.timing;
+        game
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let rules = &self.rules;
+        check(
+            // at 1 every game is decided on the first point, deuce and the deciding game's
+            // end change would never come up
+            rules.points_to_win >= 2,
+            "rules.points_to_win",
+            "at least 2",
+            rules.points_to_win,
+        )?;
+        check(
+            rules.best_of % 2 == 1,
+            "rules.best_of",
+            "an odd number",
+            rules.best_of,
+        )?;
+
+        let timing = &self.timing;
+        check(
+            timing.frame_variation <= timing.frame_delay,
+            "timing.frame_variation",
+            "no more than timing.frame_delay",
+            timing.frame_variation,
+        )?;
+
+        let physics = &self.physics;
+        positive("physics.min_speed", physics.min_speed)?;
+        check(
+            physics.max_speed >= physics.min_speed && physics.max_speed.is_finite(),
+            "physics.max_speed",
+            "at least physics.min_speed",
+            physics.max_speed,
+        )?;
+        // the paddles have to fit between the walls of the smallest court
+        let largest_paddle = MIN_HEIGHT as f64 / 2.0;
+        check(
+            physics.paddle_size > 0.0 && physics.paddle_size <= largest_paddle,
+            "physics.paddle_size",
+            &format!("above 0 and at most {}", largest_paddle),
+            physics.paddle_size,
+        )?;
+        check(
+            physics.speed_up >= 1.0 && physics.speed_up.is_finite(),
+            "physics.speed_up",
+            "at least 1",
+            physics.speed_up,
+        )?;
+        not_negative("physics.hit_angle", physics.hit_angle)?;
+        not_negative("physics.hit_spin", physics.hit_spin)?;
+        not_negative("physics.spin_drift", physics.spin_drift)?;
+        not_negative("physics.reach_penalty", physics.reach_penalty)?;
+        fraction("physics.wall_damping", physics.wall_damping)?;
+        fraction("physics.wall_spin_damping", physics.wall_spin_damping)?;
+        fraction("physics.net_cord_chance", physics.net_cord_chance)?;
+        fraction("physics.net_cord_drop_chance", physics.net_cord_drop_chance)?;
+        fraction("physics.net_cord_drop_speed", physics.net_cord_drop_speed)?;
+        fraction("physics.net_cord_roll_speed", physics.net_cord_roll_speed)?;
+        check(
+            physics.net_cord_kick >= 1.0 && physics.net_cord_kick.is_finite(),
+            "physics.net_cord_kick",
+            "at least 1",
+            physics.net_cord_kick,
+        )?;
+        not_negative("physics.net_cord_wobble", physics.net_cord_wobble)?;
+
+        for (name, tuning) in &self.skills {
+            if Skill::from_name(name).is_none() {
+                return Err(format!(
+                    "skills.{}: unknown skill, expected one of {}",
+                    name,
+                    SKILLS.map(|skill| skill.name).join(", ")
+                ));
+            }
+            let key = |field: &str| format!("skills.{}.{}", name, field);
+            if let Some(speed) = tuning.paddle_speed {
+                positive(&key("paddle_speed"), speed)?;
+            }
+            if let Some(noise) = tuning.target_noise {
+                not_negative(&key("target_noise"), noise)?;
+            }
+            if let Some(miss_base) = tuning.miss_base {
+                fraction(&key("miss_base"), miss_base)?;
+            }
+            if let Some(scaling) = tuning.difficulty_scaling {
+                not_negative(&key("difficulty_scaling"), scaling)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn check(ok: bool, key: &str, requirement: &str, value: impl Display) -> Result<(), String> {
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("{} must be {}, got {}", key, requirement, value))
+    }
+}
+
+// the comparisons are written so that nan fails them too
+fn positive(key: &str, value: f64) -> Result<(), String> {
+    check(value > 0.0 && value.is_finite(), key, "above 0", value)
+}
+
+fn not_negative(key: &str, value: f64) -> Result<(), String> {
+    check(value >= 0.0 && value.is_finite(), key, "0 or more", value)
+}
+
+fn fraction(key: &str, value: f64) -> Result<(), String> {
+    check((0.0..=1.0).contains(&value), key, "between 0 and 1", value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_printed_defaults_load_back_unchanged() {
+        let config = Config::parse(&Config::defaults_toml()).expect("the defaults are valid");
+        assert_eq!(config.rules, Rules::default());
+        assert_eq!(config.timing, Timing::default());
+        assert_eq!(config.physics, Physics::default());
+        assert_eq!(config.skill(SKILLS[0]), SKILLS[0]);
+    }
+
+    #[test]
+    fn missing_keys_keep_the_built_in_values() {
+        let config = Config::parse("[rules]\nbest_of = 5\n").expect("a valid config");
+        assert_eq!(config.rules.best_of, 5);
+        assert_eq!(config.rules.points_to_win, Rules::default().points_to_win);
+        assert_eq!(config.physics, Physics::default());
+    }
+
+    #[test]
+    fn skill_tuning_only_changes_what_it_names() {
+        let name = SKILLS[0].name;
+        let config = Config::parse(&format!("[skills.{}]\nmiss_base = 0.5\n", name))
+            .expect("a valid config");
+        let skill = config.skill(SKILLS[0]);
+        assert_eq!(skill.miss_base, 0.5);
+        assert_eq!(skill.paddle_speed, SKILLS[0].paddle_speed);
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        for (text, key) in [
+            ("[rules]\npoints_to_win = 1\n", "rules.points_to_win"),
+            ("[rules]\nbest_of = 4\n", "rules.best_of"),
+            (
+                "[timing]\nframe_delay = 10\nframe_variation = 20\n",
+                "timing.frame_variation",
+            ),
+            ("[physics]\nmin_speed = 0.0\n", "physics.min_speed"),
+            ("[physics]\nmax_speed = 1.0\n", "physics.max_speed"),
+            ("[physics]\npaddle_size = 50.0\n", "physics.paddle_size"),
+            ("[physics]\nspeed_up = 0.5\n", "physics.speed_up"),
+            ("[physics]\nhit_angle = -1.0\n", "physics.hit_angle"),
+            ("[physics]\nwall_damping = 1.5\n", "physics.wall_damping"),
+            ("[physics]\nnet_cord_kick = 0.5\n", "physics.net_cord_kick"),
+            (
+                "[physics]\nnet_cord_drop_chance = 2.0\n",
+                "physics.net_cord_drop_chance",
+            ),
+            ("[skills.nobody]\nmiss_base = 0.1\n", "skills.nobody"),
+        ] {
+            let err = Config::parse(text).expect_err(text);
+            assert!(err.starts_with(key), "{} gave {}", text, err);
+        }
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(Config::parse("[physics]\nmax_sped = 9.0\n").is_err());
+        assert!(Config::parse("[graphics]\n").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use libc::{
+    _SC_PAGESIZE, MAP_ANONYMOUS, MAP_FAILED, MAP_PRIVATE, MAP_STACK, PROT_NONE, PROT_READ,
+    PROT_WRITE, c_void, getcontext, makecontext, mmap, mprotect, munmap, swapcontext, sysconf,
+    ucontext_t,
+};
+use std::io;
+use std::ptr::NonNull;
+
+// a stack mapped for one context, with a guard page at the bottom so an overflow faults instead
+// of writing over whatever was mapped below it
+struct Stack {
+    base: NonNull<c_void>,
+    // the guard page included
+    bytes: usize,
+    guard: usize,
+}
+
+impl Stack {
+    fn new(size: usize) -> io::Result<Stack> {
+        let page = unsafe { sysconf(_SC_PAGESIZE) } as usize;
+        let bytes = size.next_multiple_of(page) + page;
+        let base = unsafe {
+            mmap(
+                std::ptr::null_mut(),
+                bytes,
+                PROT_READ | PROT_WRITE,
+                MAP_PRIVATE | MAP_ANONYMOUS | MAP_STACK,
+                -1,
+                0,
+            )
+        };
+        if base == MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        let stack = Stack {
+            base: NonNull::new(base).expect("mmap does not hand out null"),
+            bytes,
+            guard: page,
+        };
+        // stacks grow down, towards the guard
+        if unsafe { mprotect(base, page, PROT_NONE) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(stack)
+    }
+}
+
+impl Drop for Stack {
+    fn drop(&mut self) {
+        unsafe { munmap(self.base.as_ptr(), self.bytes) };
+    }
+}
+
+// somewhere to resume: registers and signal mask as getcontext(3) saves them, on any
+// architecture glibc runs on. always boxed, glibc points the saved context at its own floating
+// point state, so it must not move once saved
+pub struct Context {
+    ucontext: ucontext_t,
+    // none when the context runs on a stack it does not own, like a thread's own
+    _stack: Option<Stack>,
+}
+
+impl Context {
+    // filled in by the first switch away from whatever is running
+    pub fn saved() -> Box<Context> {
+        Box::new(Context {
+            ucontext: unsafe { std::mem::zeroed() },
+            _stack: None,
+        })
+    }
+
+    // starts `entry` on a fresh stack of `stack_size` bytes when first switched to. `entry`
+    // must never return, there is nothing to return to
+    pub fn new(entry: extern "C" fn(), stack_size: usize) -> io::Result<Box<Context>> {
+        let stack = Stack::new(stack_size)?;
+        let mut context = Context::saved();
+        let ucontext = &mut context.ucontext;
+        if unsafe { getcontext(ucontext) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        ucontext.uc_stack.ss_sp = unsafe { stack.base.as_ptr().byte_add(stack.guard) };
+        ucontext.uc_stack.ss_size = stack.bytes - stack.guard;
+        ucontext.uc_stack.ss_flags = 0;
+        ucontext.uc_link = std::ptr::null_mut();
+        unsafe { makecontext(ucontext, entry, 0) };
+        context._stack = Some(stack);
+        Ok(context)
+    }
+
+    // saves what is running into `from` and resumes `to`. comes back once something switches to
+    // `from` again. safe to call from a signal handler that interrupted `from` synchronously, the
+    // handler then returns when `from` is resumed. both must point at live contexts, `to` saved
+    // or made, and neither in use by another thread
+    pub(crate) unsafe fn switch(from: *mut Context, to: *const Context) -> io::Result<()> {
+        if unsafe { swapcontext(&mut (*from).ucontext, &(*to).ucontext) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicPtr, Ordering};
+
+    const TURNS_EACH: usize = 300;
+    const STACK: usize = 256 * 1024;
+
+    // ping's and pong's contexts, then the test's own
+    static SLOTS: [AtomicPtr<Context>; 3] = [const { AtomicPtr::new(std::ptr::null_mut()) }; 3];
+    static PLAYED: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+    extern "C" fn ping() {
+        take_turns(0)
+    }
+
+    extern "C" fn pong() {
+        take_turns(1)
+    }
+
+    // plays a turn and switches to the other player, or back to the test once both are done
+    fn take_turns(me: usize) -> ! {
+        loop {
+            let played = {
+                let mut played = PLAYED.lock().expect("no turn panics");
+                played.push(me);
+                played.len()
+            };
+            let next = if played == 2 * TURNS_EACH { 2 } else { 1 - me };
+            let from = SLOTS[me].load(Ordering::SeqCst);
+            let to = SLOTS[next].load(Ordering::SeqCst);
+            unsafe { Context::switch(from, to) }.expect("the contexts are valid");
+        }
+    }
+
+    #[test]
+    fn two_contexts_take_turns_on_their_own_stacks() {
+        let contexts = [
+            Context::new(ping, STACK).expect("a stack maps"),
+            Context::new(pong, STACK).expect("a stack maps"),
+            Context::saved(),
+        ];
+        let contexts = contexts.map(Box::into_raw);
+        for (slot, context) in SLOTS.iter().zip(contexts) {
+            slot.store(context, Ordering::SeqCst);
+        }
+
+        unsafe { Context::switch(contexts[2], contexts[0]) }.expect("the contexts are valid");
+
+        let played = PLAYED.lock().expect("no turn panics");
+        assert_eq!(played.len(), 2 * TURNS_EACH);
+        for (n, &side) in played.iter().enumerate() {
+            assert_eq!(side, n % 2, "turn {}", n);
+        }
+        // ping and pong are left suspended mid-turn, nothing switches to them again
+        for context in contexts {
+            drop(unsafe { Box::from_raw(context) });
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::game::{End, GameState, Inputs, Side};
+use crate::human::{Human, Keys};
+use crate::physics::predict_landing;
+use crate::replay::Replay;
+use crate::skill::Skill;
+use crate::term::Key;
+
+// decides how one paddle moves, once per frame. Send, as ping and pong can play on threads of
+// their own
+pub trait PaddleController: Send {
+    // row delta for this frame, update_paddles caps it at the side's paddle speed
+    fn decide(&mut self, game: &GameState, side: Side) -> f64;
+
+    // a key typed since the last frame
+    fn press(&mut self, _key: Key) {}
+
+    // called while a point or let is being shown, so nothing carries into the next serve
+    fn reset(&mut self) {}
+
+    fn is_human(&self) -> bool {
+        false
+    }
+}
+
+pub type Controllers = [Box<dyn PaddleController>; 2];
+
+pub fn decide(controllers: &mut Controllers, game: &GameState) -> Inputs {
+    Inputs {
+        ping: controllers[Side::Ping.index()].decide(game, Side::Ping),
+        pong: controllers[Side::Pong.index()].decide(game, Side::Pong),
+    }
+}
+
+// the row to meet the ball at, once it is in this end's half and heading for its paddle
+fn incoming(game: &GameState, end: End) -> Option<f64> {
+    let ball = &game.ball;
+    let half_way = game.court.width as f64 / 2.0;
+    let in_half = match end {
+        End::Left => ball.x < half_way,
+        End::Right => ball.x > half_way,
+    };
+    if !in_half {
+        return None;
+    }
+    predict_landing(game, end).map(|landing| landing.y)
+}
+
+// full speed towards `target`, without overshooting it
+fn step_towards(paddle_y: f64, target: f64, speed: f64) -> f64 {
+    (target - paddle_y).clamp(-speed, speed)
+}
+
+// how far off the ball a player of this skill aims this frame
+fn aim_error(rng: &mut StdRng, skill: &Skill) -> f64 {
+    if skill.target_noise > 0.0 {
+        rng.random_range(-skill.target_noise..skill.target_noise)
+    } else {
+        0.0
+    }
+}
+
+// each side draws from its own stream, derived from the match seed
+fn side_rng(game: &GameState, side: Side) -> StdRng {
+    StdRng::seed_from_u64(
+        game.seed ^ 0x9e37_79b9_7f4a_7c15_u64.wrapping_mul(side.index() as u64 + 1),
+    )
+}
+
+// the original strategy: chase a noisy prediction, drift to the middle otherwise
+pub struct Classic {
+    rng: StdRng,
+}
+
+impl PaddleController for Classic {
+    fn decide(&mut self, game: &GameState, side: Side) -> f64 {
+        let end = game.end_of(side);
+        let paddle_y = game.paddle_y[side.index()];
+        let skill = &game.skills[side.index()];
+
+        let target_y =
+            incoming(game, end).unwrap_or(game.court.center()) + aim_error(&mut self.rng, skill);
+
+        if (paddle_y - target_y).abs() > 0.1 {
+            if paddle_y < target_y {
+                skill.paddle_speed
+            } else {
+                -skill.paddle_speed
+            }
+        } else {
+            0.0
+        }
+    }
+}
+
+// no hesitation and no aiming error whatever its skill: always heads straight for the predicted row
+pub struct Perfect;
+
+impl PaddleController for Perfect {
+    fn decide(&mut self, game: &GameState, side: Side) -> f64 {
+        let center = game.court.center();
+        let target_y = incoming(game, game.end_of(side)).unwrap_or(center);
+        let speed = game.skills[side.index()].paddle_speed;
+        step_towards(game.paddle_y[side.index()], target_y, speed)
+    }
+}
+
+// ball within this fraction of the court's width from the baseline wakes the lazy player up
+const LAZY_REACTION_DISTANCE: f64 = 0.25;
+
+// ambles back to the middle and only reacts once the ball is nearly on it
+pub struct Lazy {
+    rng: StdRng,
+}
+
+impl PaddleController for Lazy {
+    fn decide(&mut self, game: &GameState, side: Side) -> f64 {
+        let ball = &game.ball;
+        let end = game.end_of(side);
+        let paddle_y = game.paddle_y[side.index()];
+        let skill = &game.skills[side.index()];
+
+        let court = &game.court;
+        let close =
+            (ball.x - court.paddle_plane(end)).abs() < court.width as f64 * LAZY_REACTION_DISTANCE;
+        match incoming(game, end) {
+            Some(landing_y) if close => step_towards(
+                paddle_y,
+                landing_y + aim_error(&mut self.rng, skill),
+                skill.paddle_speed,
+            ),
+            _ => step_towards(paddle_y, court.center(), skill.paddle_speed / 2.0),
+        }
+    }
+}
+
+// how far from the paddle centre the aggressive player tries to take the ball,
+// as a fraction of the paddle size. further out means a steeper return and a likelier miss
+const AGGRESSIVE_HIT_OFFSET: f64 = 0.6;
+
+// meets the ball off-centre so the return angles away from the opponent's paddle
+pub struct Aggressive {
+    rng: StdRng,
+}
+
+impl PaddleController for Aggressive {
+    fn decide(&mut self, game: &GameState, side: Side) -> f64 {
+        let end = game.end_of(side);
+        let paddle_y = game.paddle_y[side.index()];
+        let skill = &game.skills[side.index()];
+
+        let center = game.court.center();
+        let Some(landing_y) = incoming(game, end) else {
+            return step_towards(paddle_y, center, skill.paddle_speed);
+        };
+
+        // a positive hit offset sends the ball downwards, so aim away from wherever the opponent is
+        let opponent_y = game.paddle_y[side.opponent().index()];
+        let direction = if opponent_y < center {
+            1.0
+        } else if opponent_y > center {
+            -1.0
+        } else if self.rng.random_bool(0.5) {
+            1.0
+        } else {
+            -1.0
+        };
+
+        let target_y = landing_y - direction * AGGRESSIVE_HIT_OFFSET * game.physics.paddle_size
+            + aim_error(&mut self.rng, skill);
+        step_towards(paddle_y, target_y, skill.paddle_speed)
+    }
+}
+
+// plays a fixed list of deltas, starting over when it runs out
+pub struct Scripted {
+    moves: Vec<f64>,
+    next: usize,
+}
+
+impl PaddleController for Scripted {
+    fn decide(&mut self, _game: &GameState, _side: Side) -> f64 {
+        if self.moves.is_empty() {
+            return 0.0;
+        }
+        let delta = self.moves[self.next % self.moves.len()];
+        self.next += 1;
+        delta
+    }
+}
+
+// follows the paddle positions of a recorded match, frame by frame
+pub struct Playback {
+    track: Vec<f64>,
+    next: usize,
+}
+
+impl PaddleController for Playback {
+    fn decide(&mut self, game: &GameState, side: Side) -> f64 {
+        // frame 0 is the starting position, frame n is where the paddle was after step n
+        self.next += 1;
+        match self.track.get(self.next) {
+            Some(&target_y) => target_y - game.paddle_y[side.index()],
+            None => 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ControllerKind {
+    #[default]
+    Classic,
+    Perfect,
+    Lazy,
+    Aggressive,
+    Human,
+    Scripted(Vec<f64>),
+    // paddle rows for one side of a recorded match
+    Playback(Vec<f64>),
+}
+
+pub const CONTROLLER_HELP: &str =
+    "classic, perfect, lazy, aggressive, human, script:DELTA,DELTA,.. or replay:FILE";
+
+impl ControllerKind {
+    // `replay:FILE` reads the recording straight away, so a bad file is reported up front
+    pub fn parse(spec: &str, side: Side) -> Result<ControllerKind, String> {
+        Ok(match spec.split_once(':') {
+            None => match spec {
+                "classic" => ControllerKind::Classic,
+                "perfect" => ControllerKind::Perfect,
+                "lazy" => ControllerKind::Lazy,
+                "aggressive" => ControllerKind::Aggressive,
+                "human" => ControllerKind::Human,
+                _ => return Err(format!("unknown controller {}", spec)),
+            },
+            Some(("script", moves)) => ControllerKind::Scripted(
+                moves
+                    .split(',')
+                    .map(|delta| delta.trim().parse::<f64>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("bad script {}", moves))?,
+            ),
+            Some(("replay", path)) => {
+                let replay = Replay::load(path)
+                    .map_err(|err| format!("cannot load replay {}: {}", path, err))?;
+                ControllerKind::Playback(
+                    replay
+                        .frames
+                        .iter()
+                        .map(|frame| frame.paddle_y[side.index()])
+                        .collect(),
+                )
+            }
+            Some(_) => return Err(format!("unknown controller {}", spec)),
+        })
+    }
+
+    // `keys` decides which keys a human listens to
+    fn build(&self, game: &GameState, side: Side, keys: Keys) -> Box<dyn PaddleController> {
+        match self {
+            ControllerKind::Classic => Box::new(Classic {
+                rng: side_rng(game, side),
+            }),
+            ControllerKind::Perfect => Box::new(Perfect),
+            ControllerKind::Lazy => Box::new(Lazy {
+                rng: side_rng(game, side),
+            }),
+            ControllerKind::Aggressive => Box::new(Aggressive {
+                rng: side_rng(game, side),
+            }),
+            ControllerKind::Human => Box::new(Human::new(keys)),
+            ControllerKind::Scripted(moves) => Box::new(Scripted {
+                moves: moves.clone(),
+                next: 0,
+            }),
+            ControllerKind::Playback(track) => Box::new(Playback {
+                track: track.clone(),
+                next: 0,
+            }),
+        }
+    }
+}
+
+// fresh controllers for a match. two humans split the keyboard, ping on w/s and pong on the arrows
+pub fn build_controllers(kinds: &[ControllerKind; 2], game: &GameState) -> Controllers {
+    let both_human = kinds.iter().all(|kind| *kind == ControllerKind::Human);
+    let keys = |side: Side| match side {
+        _ if !both_human => Keys::Any,
+        Side::Ping => Keys::Letters,
+        Side::Pong => Keys::Arrows,
+    };
+
+    [
+        kinds[0].build(game, Side::Ping, keys(Side::Ping)),
+        kinds[1].build(game, Side::Pong, keys(Side::Pong)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::step;
+    use crate::replay::Recorder;
+
+    #[test]
+    fn names_and_scripts_parse() {
+        assert_eq!(
+            ControllerKind::parse("lazy", Side::Ping),
+            Ok(ControllerKind::Lazy)
+        );
+        assert_eq!(
+            ControllerKind::parse("script:1,-0.5, 2", Side::Ping),
+            Ok(ControllerKind::Scripted(vec![1.0, -0.5, 2.0]))
+        );
+        for spec in ["script:1,up", "script:", "script:1,,2"] {
+            let err = ControllerKind::parse(spec, Side::Ping).expect_err(spec);
+            assert!(err.starts_with("bad script"), "{}", err);
+        }
+        for spec in ["foo:bar", "robot"] {
+            assert_eq!(
+                ControllerKind::parse(spec, Side::Pong),
+                Err(format!("unknown controller {}", spec))
+            );
+        }
+    }
+
+    #[test]
+    fn a_missing_replay_is_reported_when_parsed() {
+        let err = ControllerKind::parse("replay:/nonexistent/match.txt", Side::Ping)
+            .expect_err("there is no such file");
+        assert!(
+            err.starts_with("cannot load replay /nonexistent/match.txt"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn a_script_starts_over_when_it_runs_out() {
+        let game = GameState::with_seed(1);
+        let mut script = Scripted {
+            moves: vec![1.0, -1.0, 0.5],
+            next: 0,
+        };
+        let moves: Vec<f64> = (0..5).map(|_| script.decide(&game, Side::Ping)).collect();
+        assert_eq!(moves, [1.0, -1.0, 0.5, 1.0, -1.0]);
+
+        let mut empty = Scripted {
+            moves: Vec::new(),
+            next: 0,
+        };
+        assert_eq!(empty.decide(&game, Side::Ping), 0.0);
+    }
+
+    #[test]
+    fn playback_puts_each_paddle_where_the_recording_had_it_after_the_same_step() {
+        let path = std::env::temp_dir().join(format!("playback-test-{}.txt", std::process::id()));
+        let mut game = GameState::with_seed(6);
+        game.rules.points_to_win = 3;
+        let mut recorder = Recorder::create(&path, &game).expect("the temp dir is writable");
+        let mut players =
+            build_controllers(&[ControllerKind::Classic, ControllerKind::Lazy], &game);
+        let mut recorded = vec![game.paddle_y];
+        while !game.game_over {
+            let inputs = decide(&mut players, &game);
+            let events = step(&mut game, &inputs);
+            recorder
+                .record(&game, &events)
+                .expect("the temp dir is writable");
+            recorded.push(game.paddle_y);
+        }
+        recorder.finish().expect("the temp dir is writable");
+
+        let spec = format!("replay:{}", path.display());
+        let kinds = [
+            ControllerKind::parse(&spec, Side::Ping),
+            ControllerKind::parse(&spec, Side::Pong),
+        ];
+        std::fs::remove_file(&path).ok();
+        let kinds = kinds.map(|kind| kind.expect("the recording reads back"));
+
+        let mut game = GameState::with_seed(6);
+        game.rules.points_to_win = 3;
+        let mut players = build_controllers(&kinds, &game);
+        for expected in &recorded[1..] {
+            let inputs = decide(&mut players, &game);
+            step(&mut game, &inputs);
+            for side in [Side::Ping, Side::Pong] {
+                let i = side.index();
+                assert!(
+                    (game.paddle_y[i] - expected[i]).abs() < 1e-9,
+                    "{} at frame {}: {} against {}",
+                    side,
+                    game.frame,
+                    game.paddle_y[i],
+                    expected[i]
+                );
+            }
+        }
+        assert!(game.game_over);
+
+        // past the end of the recording the paddles stay put
+        assert_eq!(players[0].decide(&game, Side::Ping), 0.0);
+    }
+}
@@ -0,0 +1,166 @@
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::game::End;
+use crate::{HEIGHT, WIDTH};
+
+// anything smaller leaves no room between the paddles, the net and the walls
+pub const MIN_WIDTH: usize = 30;
+pub const MIN_HEIGHT: usize = 10;
+
+// terminal rows the board does not get: the header above it, the border below it
+// and room for the messages, prompts and final score underneath
+const CHROME_ROWS: usize = 16;
+// the side borders
+const CHROME_COLUMNS: usize = 2;
+
+// the playing area in cells. the top and bottom rows are the walls
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Court {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for Court {
+    fn default() -> Self {
+        Court {
+            width: WIDTH,
+            height: HEIGHT,
+        }
+    }
+}
+
+impl Court {
+    pub fn new(width: usize, height: usize) -> Result<Court, String> {
+        if width < MIN_WIDTH || height < MIN_HEIGHT {
+            return Err(format!(
+                "court {}x{} is too small, it needs at least {}x{}",
+                width, height, MIN_WIDTH, MIN_HEIGHT
+            ));
+        }
+        Ok(Court { width, height })
+    }
+
+    // the biggest court that fits a terminal of `columns` x `rows`. a terminal without room for
+    // the smallest court gets none, the board would wrap and every diff would land off target
+    pub fn fit(columns: usize, rows: usize) -> Result<Court, String> {
+        let (min_columns, min_rows) = (MIN_WIDTH + CHROME_COLUMNS, MIN_HEIGHT + CHROME_ROWS);
+        if columns < min_columns || rows < min_rows {
+            return Err(format!(
+                "terminal {}x{} is too small, the board needs at least {}x{}",
+                columns, rows, min_columns, min_rows
+            ));
+        }
+        Ok(Court {
+            width: columns - CHROME_COLUMNS,
+            height: rows - CHROME_ROWS,
+        })
+    }
+
+    pub fn net(&self) -> usize {
+        self.width / 2
+    }
+
+    // where the paddles wait and the ball starts
+    pub fn center(&self) -> f64 {
+        (self.height / 2) as f64
+    }
+
+    // the rows the ball bounces off
+    pub fn top_wall(&self) -> f64 {
+        1.0
+    }
+
+    pub fn bottom_wall(&self) -> f64 {
+        (self.height - 2) as f64
+    }
+
+    // the rows a paddle centre can reach, `paddle_size` from either edge
+    pub fn paddle_range(&self, paddle_size: f64) -> (f64, f64) {
+        (paddle_size, self.height as f64 - paddle_size)
+    }
+
+    // column of the paddle face at each end
+    pub fn paddle_plane(&self, end: End) -> f64 {
+        match end {
+            End::Left => 1.0,
+            End::Right => (self.width - 2) as f64,
+        }
+    }
+
+    // moves a column from this court to the same place on `other`. each half is stretched on
+    // its own so the net and both paddle faces land exactly on their counterparts
+    pub fn rescale_x(&self, x: f64, other: &Court) -> f64 {
+        let net = self.net() as f64;
+        let other_net = other.net() as f64;
+        if x < net {
+            let left = self.paddle_plane(End::Left);
+            let other_left = other.paddle_plane(End::Left);
+            other_left + (x - left) * (other_net - other_left) / (net - left)
+        } else {
+            let right = self.paddle_plane(End::Right);
+            let other_right = other.paddle_plane(End::Right);
+            other_net + (x - net) * (other_right - other_net) / (right - net)
+        }
+    }
+
+    // same for a row, between the walls
+    pub fn rescale_y(&self, y: f64, other: &Court) -> f64 {
+        other.top_wall()
+            + (y - self.top_wall()) * (other.bottom_wall() - other.top_wall())
+                / (self.bottom_wall() - self.top_wall())
+    }
+}
+
+impl fmt::Display for Court {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+// WIDTHxHEIGHT, as taken by --court
+impl FromStr for Court {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Court, String> {
+        let parsed = s
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+        match parsed {
+            Some((width, height)) => Court::new(width, height),
+            None => Err(format!("bad court size {}, expected WIDTHxHEIGHT", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_court_fits_inside_the_terminal_with_its_chrome() {
+        let court = Court::fit(100, 40).expect("a roomy terminal");
+        assert_eq!(court, Court::new(98, 24).expect("a valid court"));
+
+        let smallest = Court::fit(MIN_WIDTH + CHROME_COLUMNS, MIN_HEIGHT + CHROME_ROWS)
+            .expect("just enough room");
+        assert_eq!((smallest.width, smallest.height), (MIN_WIDTH, MIN_HEIGHT));
+    }
+
+    #[test]
+    fn a_terminal_below_the_minimum_fits_no_court() {
+        for (columns, rows) in [
+            (MIN_WIDTH + CHROME_COLUMNS - 1, 40),
+            (100, MIN_HEIGHT + CHROME_ROWS - 1),
+            (0, 0),
+        ] {
+            let err = Court::fit(columns, rows).expect_err("too small");
+            assert!(
+                err.starts_with(&format!("terminal {}x{} is too small", columns, rows)),
+                "{}",
+                err
+            );
+        }
+    }
+}
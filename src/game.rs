@@ -0,0 +1,1013 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::court::Court;
+use crate::physics::{
+    Physics, ensure_minimum_ball_speed, handle_paddle_hit, handle_potential_miss, update_ball,
+    update_paddles,
+};
+use crate::skill::Skill;
+use crate::{MAX_SCORE, Timing};
+
+// rows next to each wall that are out of bounds for a serve
+const SERVICE_MARGIN: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Ping,
+    Pong,
+}
+
+impl Side {
+    pub fn index(self) -> usize {
+        match self {
+            Side::Ping => 0,
+            Side::Pong => 1,
+        }
+    }
+
+    pub fn opponent(self) -> Side {
+        match self {
+            Side::Ping => Side::Pong,
+            Side::Pong => Side::Ping,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Side::Ping => "ping",
+            Side::Pong => "pong",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Side> {
+        match name {
+            "ping" => Some(Side::Ping),
+            "pong" => Some(Side::Pong),
+            _ => None,
+        }
+    }
+}
+
+// a physical end of the table. ping starts on the left and the players change ends between games
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum End {
+    Left,
+    Right,
+}
+
+impl End {
+    pub fn opposite(self) -> End {
+        match self {
+            End::Left => End::Right,
+            End::Right => End::Left,
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    // waiting for the server to launch the ball
+    Serve,
+    // the serve is travelling and the receiver has not touched it yet
+    ServeInPlay,
+    Rally,
+    PointEnd,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointReason {
+    // the player whose paddle missed
+    Miss(Side),
+    // the player on whose side the ball left play, over the baseline or back off the net
+    Out(Side),
+    // the server, whose serve landed wide of the receiver's paddle area or was dropped back
+    // on their own side by the net
+    Fault(Side),
+}
+
+impl PointReason {
+    pub fn name(self) -> &'static str {
+        match self {
+            PointReason::Miss(_) => "miss",
+            PointReason::Out(_) => "out",
+            PointReason::Fault(_) => "fault",
+        }
+    }
+
+    // the player who lost the point
+    pub fn side(self) -> Side {
+        match self {
+            PointReason::Miss(side) | PointReason::Out(side) | PointReason::Fault(side) => side,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    Serve(Side),
+    // the serve clipped the net and still landed, so the server serves again
+    Let(Side),
+    PaddleHit(Side),
+    WallBounce,
+    NetCord,
+    // the player whose paddle reached the ball and fluffed it, just before the point is scored
+    Miss(Side),
+    // the player on whose side the ball left play, just before the point is scored
+    Out(Side),
+    PointScored { winner: Side, reason: PointReason },
+    // the serve passed to this player for the next point
+    ServeChange(Side),
+    Handoff(Side),
+    GameWon { winner: Side },
+    EndsChanged,
+    GameOver { winner: Side },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Serve(_) => "serve",
+            Event::Let(_) => "let",
+            Event::PaddleHit(_) => "paddle_hit",
+            Event::WallBounce => "wall_bounce",
+            Event::NetCord => "net_cord",
+            Event::Miss(_) => "miss",
+            Event::Out(_) => "out",
+            Event::PointScored { .. } => "point_scored",
+            Event::ServeChange(_) => "serve_change",
+            Event::Handoff(_) => "handoff",
+            Event::GameWon { .. } => "game_won",
+            Event::EndsChanged => "ends_changed",
+            Event::GameOver { .. } => "game_over",
+        }
+    }
+
+    // the player the event is about, the winner for points, games and the match
+    pub fn side(&self) -> Option<Side> {
+        match *self {
+            Event::Serve(side)
+            | Event::Let(side)
+            | Event::PaddleHit(side)
+            | Event::Miss(side)
+            | Event::Out(side)
+            | Event::ServeChange(side)
+            | Event::Handoff(side) => Some(side),
+            Event::PointScored { winner, .. }
+            | Event::GameWon { winner }
+            | Event::GameOver { winner } => Some(winner),
+            Event::WallBounce | Event::NetCord | Event::EndsChanged => None,
+        }
+    }
+}
+
+// ittf style scoring: a game goes to `points_to_win` with a two point lead, a match is best of n games
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    pub points_to_win: u32,
+    pub best_of: u32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            points_to_win: MAX_SCORE,
+            best_of: 1,
+        }
+    }
+}
+
+impl Rules {
+    pub fn games_to_win(&self) -> u32 {
+        self.best_of / 2 + 1
+    }
+}
+
+// how far each paddle moves this frame in rows, negative is up. capped at the paddle speed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Inputs {
+    pub ping: f64,
+    pub pong: f64,
+}
+
+impl Inputs {
+    pub fn delta(&self, side: Side) -> f64 {
+        match side {
+            Side::Ping => self.ping,
+            Side::Pong => self.pong,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Ball {
+    pub x: f64,
+    pub y: f64,
+    pub dx: f64,
+    pub dy: f64,
+    // affects trajectory (flugbahn) and bounce behavior
+    pub spin: f64,
+}
+
+impl Ball {
+    pub fn speed(&self) -> f64 {
+        (self.dx * self.dx + self.dy * self.dy).sqrt()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GameState {
+    pub ball: Ball,
+    // per-player arrays are indexed by Side::index
+    pub paddle_y: [f64; 2],
+    // points in the current game
+    pub score: [u32; 2],
+    // games won in the match
+    pub games: [u32; 2],
+    pub rules: Rules,
+    pub skills: [Skill; 2],
+    pub court: Court,
+    pub physics: Physics,
+    pub timing: Timing,
+    pub game_number: u32,
+    // ping plays from the right while this is set
+    pub ends_swapped: bool,
+    pub rally_length: u32,
+    pub longest_rally: u32,
+    pub phase: Phase,
+    pub serving_player: Side,
+    // who served first in the current game, the other player starts the next one
+    pub first_server: Side,
+    // whose half is being played, flip or flop in the signal front-end
+    pub turn: Side,
+    pub game_over: bool,
+    // frames stepped so far, counted across games
+    pub frame: u64,
+    // every random decision in the simulation comes from this, so a seed replays a match exactly
+    pub seed: u64,
+    pub(crate) rng: StdRng,
+    // frame pacing draws from its own stream so headless and animated runs of a seed match
+    pub(crate) delay_rng: StdRng,
+    pub(crate) consecutive_net_hits: u8,
+    consecutive_static_frames: u32,
+    serve_touched_net: bool,
+    game_finished: bool,
+    decider_ends_changed: bool,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameState {
+    pub fn new() -> Self {
+        Self::with_seed(rand::rng().random())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rules(seed, Rules::default())
+    }
+
+    pub fn with_rules(seed: u64, rules: Rules) -> Self {
+        Self::with_court(seed, rules, Court::default())
+    }
+
+    pub fn with_court(seed: u64, rules: Rules, court: Court) -> Self {
+        let mut game = GameState {
+            ball: Ball {
+                x: 1.0,
+                y: court.center(),
+                dx: 1.0,
+                dy: 0.0,
+                spin: 0.0,
+            },
+            paddle_y: [court.center(); 2],
+            score: [0; 2],
+            games: [0; 2],
+            rules,
+            skills: [Skill::default(); 2],
+            court,
+            physics: Physics::default(),
+            timing: Timing::default(),
+            game_number: 1,
+            ends_swapped: false,
+            rally_length: 0,
+            longest_rally: 0,
+            phase: Phase::Serve,
+            serving_player: Side::Ping,
+            first_server: Side::Ping,
+            turn: Side::Ping,
+            game_over: false,
+            frame: 0,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            delay_rng: StdRng::seed_from_u64(seed.rotate_left(32)),
+            consecutive_net_hits: 0,
+            consecutive_static_frames: 0,
+            serve_touched_net: false,
+            game_finished: false,
+            decider_ends_changed: false,
+        };
+        reset_for_serve(&mut game, &mut Vec::new());
+        game
+    }
+
+    // carries on the same point on a court of another size, e.g. after the terminal was resized.
+    // ball and paddles keep their place relative to the net, paddle faces and walls
+    pub fn resize(&mut self, court: Court) {
+        let old = self.court;
+        self.ball.x = old.rescale_x(self.ball.x, &court);
+        self.ball.y = old.rescale_y(self.ball.y, &court);
+
+        let (lowest, highest) = court.paddle_range(self.physics.paddle_size);
+        for paddle_y in &mut self.paddle_y {
+            *paddle_y = old.rescale_y(*paddle_y, &court).clamp(lowest, highest);
+        }
+        self.court = court;
+    }
+
+    pub fn end_of(&self, side: Side) -> End {
+        match (side, self.ends_swapped) {
+            (Side::Ping, false) | (Side::Pong, true) => End::Left,
+            _ => End::Right,
+        }
+    }
+
+    pub fn player_at(&self, end: End) -> Side {
+        if self.end_of(Side::Ping) == end {
+            Side::Ping
+        } else {
+            Side::Pong
+        }
+    }
+
+    // both players have reached game point, so the game can only be won by two
+    pub fn is_deuce(&self) -> bool {
+        let game_point = self.rules.points_to_win - 1;
+        self.score[0] >= game_point && self.score[1] >= game_point
+    }
+
+    pub fn is_deciding_game(&self) -> bool {
+        self.rules.best_of > 1 && self.game_number == self.rules.best_of
+    }
+
+    pub fn winner(&self) -> Option<Side> {
+        if !self.game_over {
+            return None;
+        }
+        [Side::Ping, Side::Pong]
+            .into_iter()
+            .find(|side| self.games[side.index()] >= self.rules.games_to_win())
+    }
+
+    // serve changes every two points, and every point once the game reaches deuce
+    fn server_for_score(&self) -> Side {
+        let total = self.score[0] + self.score[1];
+        let deuce_at = 2 * (self.rules.points_to_win - 1);
+        let changes = if total < deuce_at {
+            total / 2
+        } else {
+            deuce_at / 2 + (total - deuce_at)
+        };
+
+        if changes.is_multiple_of(2) {
+            self.first_server
+        } else {
+            self.first_server.opponent()
+        }
+    }
+}
+
+// advances the game by one frame of the current turn
+pub fn step(game: &mut GameState, inputs: &Inputs) -> Vec<Event> {
+    let mut events = Vec::new();
+    if game.game_over {
+        return events;
+    }
+    game.frame += 1;
+
+    match game.phase {
+        Phase::PointEnd => {
+            reset_for_serve(game, &mut events);
+            return events;
+        }
+        Phase::Serve => serve(game, &mut events),
+        Phase::ServeInPlay | Phase::Rally => {}
+    }
+
+    let side = game.turn;
+    let end = game.end_of(side);
+    let net = game.court.net() as f64;
+    let last_ball_x = game.ball.x;
+    let last_ball_y = game.ball.y;
+
+    update_paddles(game, inputs);
+    let sweep = update_ball(game, &mut events);
+
+    if events.contains(&Event::NetCord) && game.ball.dx.signum() != sweep.dx.signum() {
+        // the net dropped the ball back where it came from, a serve that does is a fault
+        let hitter = game.player_at(if sweep.dx > 0.0 {
+            End::Left
+        } else {
+            End::Right
+        });
+        let reason = if game.phase == Phase::ServeInPlay {
+            PointReason::Fault(hitter)
+        } else {
+            events.push(Event::Out(hitter));
+            PointReason::Out(hitter)
+        };
+        score_point(game, hitter.opponent(), reason, &mut events);
+        return events;
+    }
+    if game.phase == Phase::ServeInPlay && events.contains(&Event::NetCord) {
+        game.serve_touched_net = true;
+    }
+
+    // a ball fast enough to cross the far paddle's column in the same frame would pass it
+    // unseen, that paddle is only tested on its own turn. it stops at the net instead and the
+    // opponent's next frame sweeps it from there
+    let far = end.opposite();
+    let court = game.court;
+    if sweep
+        .crossing(&court, court.paddle_plane(far), far)
+        .is_some()
+        && !events.contains(&Event::NetCord)
+    {
+        game.ball.x = net;
+        game.ball.y = sweep.crossing(&court, net, far).unwrap_or(game.ball.y);
+    }
+
+    let ball = &mut game.ball;
+    let crossed_back = match end {
+        End::Left => ball.x > net && ball.dx < 0.0 && last_ball_x <= net,
+        End::Right => ball.x < net && ball.dx > 0.0 && last_ball_x >= net,
+    };
+    if crossed_back {
+        ball.dx = -ball.dx;
+    }
+
+    if (ball.x - last_ball_x).abs() < 0.01 && (ball.y - last_ball_y).abs() < 0.01 {
+        game.consecutive_static_frames += 1;
+        if game.consecutive_static_frames > 5 {
+            ensure_minimum_ball_speed(game);
+            game.ball.dx *= 1.5;
+            game.consecutive_static_frames = 0;
+        }
+    } else {
+        game.consecutive_static_frames = 0;
+    }
+
+    // the paddle is tested wherever the ball's path crosses its column this frame,
+    // not only when a frame happens to end on it
+    let paddle_y = game.paddle_y[side.index()];
+    let crossing = sweep.crossing(&court, court.paddle_plane(end), end);
+
+    // a serve is decided where it lands on the receiver's baseline. one landing too close to a
+    // wall has gone out, however the receiver stands. a serve inside the area is theirs to return
+    if game.phase == Phase::ServeInPlay
+        && side != game.serving_player
+        && let Some(crossing_y) = crossing
+        && (crossing_y < court.top_wall() + SERVICE_MARGIN
+            || crossing_y > court.bottom_wall() - SERVICE_MARGIN)
+    {
+        let server = game.serving_player;
+        game.ball.y = crossing_y;
+        score_point(game, side, PointReason::Fault(server), &mut events);
+        return events;
+    }
+
+    if let Some(crossing_y) = crossing
+        && (crossing_y - paddle_y).abs() <= game.physics.paddle_size
+    {
+        game.ball.y = crossing_y;
+        if handle_potential_miss(game, side) {
+            events.push(Event::Miss(side));
+            score_point(game, side.opponent(), PointReason::Miss(side), &mut events);
+            return events;
+        }
+        handle_paddle_hit(game, side);
+        events.push(Event::PaddleHit(side));
+        if side != game.serving_player {
+            game.phase = Phase::Rally;
+        }
+    }
+
+    if game.ball.x < 0.0 {
+        let loser = game.player_at(End::Left);
+        events.push(Event::Out(loser));
+        score_point(game, loser.opponent(), PointReason::Out(loser), &mut events);
+        return events;
+    }
+    if game.ball.x >= court.width as f64 {
+        let loser = game.player_at(End::Right);
+        events.push(Event::Out(loser));
+        score_point(game, loser.opponent(), PointReason::Out(loser), &mut events);
+        return events;
+    }
+
+    let handed_off = match end {
+        End::Left => game.ball.x >= net && game.ball.dx > 0.0,
+        End::Right => game.ball.x <= net && game.ball.dx < 0.0,
+    };
+    if handed_off && game.phase == Phase::ServeInPlay && game.serve_touched_net {
+        // the serve made it over after touching the net
+        let server = game.serving_player;
+        place_for_serve(game, server);
+        events.push(Event::Let(server));
+    } else if handed_off {
+        game.turn = side.opponent();
+        game.consecutive_static_frames = 0;
+        events.push(Event::Handoff(game.turn));
+    }
+
+    events
+}
+
+fn serve(game: &mut GameState, events: &mut Vec<Event>) {
+    let server = game.serving_player;
+    let direction = match game.end_of(server) {
+        End::Left => 1.0,
+        End::Right => -1.0,
+    };
+
+    game.ball.x = game.court.paddle_plane(game.end_of(server));
+    game.ball.y = game.paddle_y[server.index()];
+    game.ball.dx = direction * game.rng.random_range(1.0..1.8);
+    game.ball.dy = game.rng.random_range(-0.7..0.7);
+    game.phase = Phase::ServeInPlay;
+    game.serve_touched_net = false;
+
+    ensure_minimum_ball_speed(game);
+    events.push(Event::Serve(server));
+}
+
+fn change_ends(game: &mut GameState, events: &mut Vec<Event>) {
+    game.ends_swapped = !game.ends_swapped;
+    events.push(Event::EndsChanged);
+}
+
+// puts the ball back on the server's paddle, ready to serve
+fn place_for_serve(game: &mut GameState, server: Side) {
+    game.phase = Phase::Serve;
+    game.serving_player = server;
+    game.turn = server;
+    game.rally_length = 0;
+    game.consecutive_static_frames = 0;
+
+    game.ball.x = game.court.paddle_plane(game.end_of(server));
+    game.ball.y = game.paddle_y[server.index()];
+    game.ball.dx = 0.0;
+    game.ball.dy = 0.0;
+    game.ball.spin = 0.0;
+}
+
+fn reset_for_serve(game: &mut GameState, events: &mut Vec<Event>) {
+    if game.game_finished {
+        game.game_finished = false;
+
+        if game
+            .games
+            .iter()
+            .any(|&won| won >= game.rules.games_to_win())
+        {
+            game.game_over = true;
+        } else {
+            game.score = [0; 2];
+            game.game_number += 1;
+            game.first_server = game.first_server.opponent();
+            game.decider_ends_changed = false;
+            change_ends(game, events);
+        }
+    } else if game.is_deciding_game()
+        && !game.decider_ends_changed
+        && game
+            .score
+            .iter()
+            .any(|&points| points >= game.rules.points_to_win / 2)
+    {
+        // in the last possible game the players change ends once either reaches half way
+        game.decider_ends_changed = true;
+        change_ends(game, events);
+    }
+
+    // a finished match has no next serve to announce, the ball just goes back to the server
+    if let Some(winner) = game.winner() {
+        let server = game.serving_player;
+        place_for_serve(game, server);
+        events.push(Event::GameOver { winner });
+        return;
+    }
+
+    let server = game.server_for_score();
+    if server != game.serving_player {
+        events.push(Event::ServeChange(server));
+    }
+    place_for_serve(game, server);
+}
+
+fn score_point(game: &mut GameState, winner: Side, reason: PointReason, events: &mut Vec<Event>) {
+    game.score[winner.index()] += 1;
+
+    if game.rally_length > game.longest_rally {
+        game.longest_rally = game.rally_length;
+    }
+
+    game.phase = Phase::PointEnd;
+    events.push(Event::PointScored { winner, reason });
+
+    let points = game.score[winner.index()];
+    let opponent_points = game.score[winner.opponent().index()];
+    if points >= game.rules.points_to_win && points - opponent_points >= 2 {
+        game.games[winner.index()] += 1;
+        game.game_finished = true;
+        events.push(Event::GameWon { winner });
+    } else {
+        let server = game.server_for_score();
+        if server != game.serving_player {
+            events.push(Event::ServeChange(server));
+        }
+        game.serving_player = server;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // scores a point for `winner` and steps once, so the next serve is set up like in a match
+    fn point(game: &mut GameState, winner: Side) -> Vec<Event> {
+        let mut events = Vec::new();
+        score_point(
+            game,
+            winner,
+            PointReason::Miss(winner.opponent()),
+            &mut events,
+        );
+        events.extend(step(game, &Inputs::default()));
+        events
+    }
+
+    #[test]
+    fn a_point_goes_to_the_winner() {
+        let mut game = GameState::with_seed(1);
+        let events = point(&mut game, Side::Pong);
+        assert_eq!(game.score, [0, 1]);
+        assert_eq!(game.phase, Phase::Serve);
+        assert!(events.contains(&Event::PointScored {
+            winner: Side::Pong,
+            reason: PointReason::Miss(Side::Ping),
+        }));
+    }
+
+    #[test]
+    fn the_game_ends_at_points_to_win() {
+        let mut game = GameState::with_seed(1);
+        for _ in 0..MAX_SCORE - 1 {
+            point(&mut game, Side::Ping);
+        }
+        assert!(!game.game_over);
+
+        let events = point(&mut game, Side::Ping);
+        assert!(events.contains(&Event::GameWon { winner: Side::Ping }));
+        assert!(events.contains(&Event::GameOver { winner: Side::Ping }));
+        assert_eq!(game.games, [1, 0]);
+        assert_eq!(game.winner(), Some(Side::Ping));
+        assert!(step(&mut game, &Inputs::default()).is_empty());
+    }
+
+    #[test]
+    fn the_serve_changes_every_two_points() {
+        let mut game = GameState::with_seed(1);
+        let mut servers = vec![game.serving_player];
+        for n in 0..6 {
+            let winner = if n % 3 == 0 { Side::Ping } else { Side::Pong };
+            point(&mut game, winner);
+            servers.push(game.serving_player);
+        }
+        use Side::{Ping, Pong};
+        assert_eq!(servers, [Ping, Ping, Pong, Pong, Ping, Ping, Pong]);
+    }
+
+    #[test]
+    fn a_serve_starts_from_the_servers_paddle() {
+        let mut game = GameState::with_seed(5);
+        let events = step(&mut game, &Inputs::default());
+        assert_eq!(events.first(), Some(&Event::Serve(Side::Ping)));
+        assert_eq!(game.phase, Phase::ServeInPlay);
+        assert!(game.ball.dx > 0.0);
+    }
+
+    #[test]
+    fn ends_change_between_games() {
+        let mut game = GameState::with_rules(
+            1,
+            Rules {
+                points_to_win: 3,
+                best_of: 3,
+            },
+        );
+        let mut events = Vec::new();
+        for _ in 0..3 {
+            events = point(&mut game, Side::Pong);
+        }
+        assert!(events.contains(&Event::EndsChanged));
+        assert_eq!(game.games, [0, 1]);
+        assert_eq!(game.score, [0, 0]);
+        assert_eq!(game.game_number, 2);
+        assert_eq!(game.end_of(Side::Ping), End::Right);
+        // ping served first in the first game, so pong starts the second
+        assert_eq!(game.serving_player, Side::Pong);
+    }
+
+    #[test]
+    fn a_match_ends_on_game_over_alone() {
+        let mut game = GameState::with_rules(
+            1,
+            Rules {
+                points_to_win: 3,
+                best_of: 1,
+            },
+        );
+        // the winning point is the fourth, the one where the serve would change hands
+        for winner in [Side::Ping, Side::Pong, Side::Pong] {
+            point(&mut game, winner);
+        }
+        let server = game.serving_player;
+        let mut events = Vec::new();
+        score_point(
+            &mut game,
+            Side::Pong,
+            PointReason::Miss(Side::Ping),
+            &mut events,
+        );
+        assert_eq!(
+            events,
+            [
+                Event::PointScored {
+                    winner: Side::Pong,
+                    reason: PointReason::Miss(Side::Ping),
+                },
+                Event::GameWon { winner: Side::Pong },
+            ]
+        );
+        assert_eq!(
+            step(&mut game, &Inputs::default()),
+            [Event::GameOver { winner: Side::Pong }]
+        );
+        assert!(game.game_over);
+        assert_eq!(game.serving_player, server);
+    }
+
+    // plays points alternately to each side until the score is `points` all
+    fn level_at(game: &mut GameState, points: u32) {
+        for _ in 0..points {
+            point(game, Side::Ping);
+            point(game, Side::Pong);
+        }
+    }
+
+    #[test]
+    fn deuce_starts_when_both_reach_game_point() {
+        let mut game = GameState::with_seed(1);
+        level_at(&mut game, 9);
+        point(&mut game, Side::Ping);
+        assert!(!game.is_deuce());
+        point(&mut game, Side::Pong);
+        assert_eq!(game.score, [10, 10]);
+        assert!(game.is_deuce());
+    }
+
+    #[test]
+    fn a_game_at_deuce_is_only_won_by_two() {
+        let mut game = GameState::with_seed(1);
+        level_at(&mut game, 10);
+        point(&mut game, Side::Ping);
+        assert_eq!(game.score, [11, 10]);
+        assert_eq!(game.games, [0, 0]);
+        point(&mut game, Side::Pong);
+        point(&mut game, Side::Pong);
+        assert_eq!(game.score, [11, 12]);
+        assert_eq!(game.games, [0, 0]);
+        let events = point(&mut game, Side::Pong);
+        assert!(events.contains(&Event::GameWon { winner: Side::Pong }));
+        assert_eq!(game.games, [0, 1]);
+        assert!(game.game_over);
+    }
+
+    #[test]
+    fn the_serve_changes_every_point_at_deuce() {
+        let mut game = GameState::with_seed(1);
+        level_at(&mut game, 10);
+        let mut servers = vec![game.serving_player];
+        for winner in [Side::Ping, Side::Pong, Side::Ping, Side::Pong] {
+            point(&mut game, winner);
+            servers.push(game.serving_player);
+        }
+        use Side::{Ping, Pong};
+        assert_eq!(servers, [Ping, Pong, Ping, Pong, Ping]);
+    }
+
+    #[test]
+    fn ends_change_half_way_through_the_deciding_game() {
+        let mut game = GameState::with_rules(
+            1,
+            Rules {
+                points_to_win: 5,
+                best_of: 3,
+            },
+        );
+        for winner in [Side::Ping, Side::Pong] {
+            for _ in 0..5 {
+                point(&mut game, winner);
+            }
+        }
+        assert!(game.is_deciding_game());
+        let ends_swapped = game.ends_swapped;
+
+        let events = point(&mut game, Side::Ping);
+        assert!(!events.contains(&Event::EndsChanged));
+        let events = point(&mut game, Side::Ping);
+        assert_eq!(game.score, [2, 0]);
+        assert!(events.contains(&Event::EndsChanged));
+        assert_ne!(game.ends_swapped, ends_swapped);
+
+        // only once
+        for winner in [Side::Pong, Side::Pong, Side::Ping] {
+            let events = point(&mut game, winner);
+            assert!(!events.contains(&Event::EndsChanged));
+        }
+    }
+
+    #[test]
+    fn earlier_games_have_no_end_change_half_way() {
+        let mut game = GameState::with_rules(
+            1,
+            Rules {
+                points_to_win: 5,
+                best_of: 3,
+            },
+        );
+        for _ in 0..4 {
+            let events = point(&mut game, Side::Ping);
+            assert!(!events.contains(&Event::EndsChanged));
+        }
+    }
+
+    // ping's ball about to clip the net, on a court where every ball passing it does
+    fn ball_at_the_net(seed: u64, phase: Phase) -> GameState {
+        let mut game = GameState::with_seed(seed);
+        game.physics.net_cord_chance = 1.0;
+        game.phase = phase;
+        let speed = game.physics.min_speed;
+        game.ball = Ball {
+            x: game.court.net() as f64 + 0.5 - speed,
+            y: game.court.center(),
+            dx: speed,
+            dy: 0.0,
+            spin: 0.0,
+        };
+        game
+    }
+
+    #[test]
+    fn the_net_cord_rolls_the_ball_over_or_drops_it_back() {
+        let (mut over, mut back) = (0, 0);
+        for seed in 0..50 {
+            let mut game = ball_at_the_net(seed, Phase::Rally);
+            let net = game.court.net() as f64;
+            let events = step(&mut game, &Inputs::default());
+            assert!(events.contains(&Event::NetCord));
+            if game.ball.x > net {
+                over += 1;
+                assert!(game.ball.dx > 0.0);
+                assert_eq!(game.phase, Phase::Rally);
+            } else {
+                back += 1;
+                assert!(game.ball.dx < 0.0);
+                assert!(events.contains(&Event::Out(Side::Ping)));
+                assert_eq!(game.score, [0, 1]);
+            }
+        }
+        assert!(over > back && back > 0, "{} over, {} back", over, back);
+    }
+
+    #[test]
+    fn the_drop_chance_decides_which_way_the_cord_sends_the_ball() {
+        for (drop_chance, dropped) in [(0.0, false), (1.0, true)] {
+            for seed in 0..10 {
+                let mut game = ball_at_the_net(seed, Phase::Rally);
+                game.physics.net_cord_drop_chance = drop_chance;
+                step(&mut game, &Inputs::default());
+                assert_eq!(game.ball.dx < 0.0, dropped);
+            }
+        }
+    }
+
+    #[test]
+    fn a_serve_the_net_drops_back_is_a_fault_and_one_it_rolls_over_a_let() {
+        let (mut faults, mut lets) = (0, 0);
+        for seed in 0..50 {
+            let mut game = ball_at_the_net(seed, Phase::ServeInPlay);
+            let mut events = step(&mut game, &Inputs::default());
+            if !events.contains(&Event::PointScored {
+                winner: Side::Pong,
+                reason: PointReason::Fault(Side::Ping),
+            }) {
+                events.extend(step(&mut game, &Inputs::default()));
+                assert!(events.contains(&Event::Let(Side::Ping)), "{:?}", events);
+                assert_eq!(game.score, [0, 0]);
+                lets += 1;
+            } else {
+                faults += 1;
+            }
+        }
+        assert!(faults > 0 && lets > 0, "{} faults, {} lets", faults, lets);
+    }
+
+    #[test]
+    fn a_serve_landing_next_to_a_wall_is_a_fault_wherever_the_receiver_stands() {
+        for (row, faulted) in [(0.3, true), (3.0, false)] {
+            let mut game = GameState::with_seed(2);
+            game.phase = Phase::ServeInPlay;
+            game.turn = Side::Pong;
+            game.skills[Side::Pong.index()].miss_base = 0.0;
+            game.skills[Side::Pong.index()].difficulty_scaling = 0.0;
+            let speed = game.physics.min_speed;
+            let y = game.court.top_wall() + row;
+            game.paddle_y[Side::Pong.index()] = y;
+            game.ball = Ball {
+                x: game.court.paddle_plane(End::Right) - speed / 2.0,
+                y,
+                dx: speed,
+                dy: 0.0,
+                spin: 0.0,
+            };
+            let fault = Event::PointScored {
+                winner: Side::Pong,
+                reason: PointReason::Fault(Side::Ping),
+            };
+            let events = step(&mut game, &Inputs::default());
+            assert_eq!(events.contains(&fault), faulted, "{:?}", events);
+            assert_eq!(events.contains(&Event::PaddleHit(Side::Pong)), !faulted);
+        }
+    }
+
+    // a rally ball at `speed` just short of the net on ping's side, straight at pong's paddle,
+    // with a pong who returns everything it reaches
+    fn fast_ball(speed: f64) -> GameState {
+        let court = Court::new(crate::court::MIN_WIDTH, crate::court::MIN_HEIGHT)
+            .expect("the smallest court is a court");
+        let mut game = GameState::with_court(2, Rules::default(), court);
+        game.physics.max_speed = speed;
+        game.physics.net_cord_chance = 0.0;
+        game.skills[Side::Pong.index()].miss_base = 0.0;
+        game.skills[Side::Pong.index()].difficulty_scaling = 0.0;
+        game.phase = Phase::Rally;
+        game.turn = Side::Ping;
+        game.ball = Ball {
+            x: court.net() as f64 - 0.5,
+            y: court.center(),
+            dx: speed,
+            dy: 0.0,
+            spin: 0.0,
+        };
+        game.paddle_y = [court.center(); 2];
+        game
+    }
+
+    #[test]
+    fn a_ball_at_the_top_speed_cannot_pass_the_far_paddle_unseen() {
+        let court_width = crate::court::MIN_WIDTH as f64;
+        for speed in [
+            crate::MAX_ALLOWED_SPEED,
+            court_width / 2.0,
+            court_width * 3.0,
+        ] {
+            let mut game = fast_ball(speed);
+            let mut events = Vec::new();
+            for _ in 0..3 {
+                events.extend(step(&mut game, &Inputs::default()));
+            }
+            assert!(
+                events.contains(&Event::PaddleHit(Side::Pong)),
+                "speed {}: {:?}",
+                speed,
+                events
+            );
+            assert!(!events.contains(&Event::Out(Side::Pong)), "speed {}", speed);
+        }
+    }
+}
@@ -0,0 +1,634 @@
+use libc::{
+    EFD_CLOEXEC, FUTEX_PRIVATE_FLAG, FUTEX_WAIT, FUTEX_WAKE, O_CLOEXEC, SIG_BLOCK, SIGUSR1,
+    SYS_futex, SYS_gettid, SYS_tgkill, c_int, c_void, close, eventfd, getpid, kill, pipe2,
+    pthread_sigmask, raise, read, sigaction, sigaddset, sigemptyset, sigset_t, sigwaitinfo,
+    syscall, write,
+};
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use libc::{REG_RIP, REG_RSP, SA_SIGINFO, siginfo_t, ucontext_t};
+use std::io;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::{AtomicI32, AtomicPtr, AtomicU32, AtomicUsize, Ordering, fence};
+use std::sync::{Condvar, Mutex};
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use std::thread::sleep;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use std::time::Duration;
+
+use crate::context::Context;
+use crate::game::Side;
+use crate::shared::Shared;
+
+// how the turn passes between ping and pong
+pub trait Handoff: Send + Sync {
+    // hands the turn to `to`. the classic trampoline never comes back from here, it jumps
+    // straight into the other player's turn
+    fn give(&self, to: Side);
+
+    // blocks until `side` has been handed the turn
+    fn wait(&self, side: Side);
+
+    // called by the thread or process about to play `side`, before its first turn
+    fn enter(&self, _side: Side) {}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Transport {
+    // SIGUSR1 to ourselves, the handler switches to the other player's context and stack
+    #[default]
+    Signal,
+    // SIGUSR1 to ourselves, the handler rewrites the saved instruction pointer. x86_64 only
+    Classic,
+    // ping and pong on threads of their own, tgkill(2) to the peer's thread
+    Tgkill,
+    EventFd,
+    Pipe,
+    Futex,
+    Condvar,
+    // ping and pong forked into processes of their own, kill(2) to the peer's pid
+    Process,
+}
+
+impl Transport {
+    pub const ALL: [Transport; 8] = [
+        Transport::Signal,
+        Transport::Classic,
+        Transport::Tgkill,
+        Transport::EventFd,
+        Transport::Pipe,
+        Transport::Futex,
+        Transport::Condvar,
+        Transport::Process,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Transport::Signal => "signal",
+            Transport::Classic => "classic",
+            Transport::Tgkill => "tgkill",
+            Transport::EventFd => "eventfd",
+            Transport::Pipe => "pipe",
+            Transport::Futex => "futex",
+            Transport::Condvar => "condvar",
+            Transport::Process => "process",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Transport> {
+        Transport::ALL
+            .into_iter()
+            .find(|transport| transport.name() == name)
+    }
+
+    // these block a player while the other one plays, so ping and pong each need a thread
+    pub fn threaded(self) -> bool {
+        matches!(
+            self,
+            Transport::Tgkill
+                | Transport::EventFd
+                | Transport::Pipe
+                | Transport::Futex
+                | Transport::Condvar
+        )
+    }
+
+    // ping and pong each need a process, with the game in shared memory
+    pub fn forks(self) -> bool {
+        self == Transport::Process
+    }
+
+    // whether this build can hand over this way at all
+    pub fn supported(self) -> bool {
+        self != Transport::Classic || cfg!(all(target_os = "linux", target_arch = "x86_64"))
+    }
+
+    // `turns` are where the signal transports start each side, indexed by Side::index.
+    // `first` is the side that starts, the others block everyone else until it hands over
+    pub fn create(self, first: Side, turns: [fn(); 2]) -> io::Result<Box<dyn Handoff>> {
+        Ok(match self {
+            Transport::Signal => Box::new(SignalHandoff::install(turns)?),
+            Transport::Classic => classic(turns)?,
+            Transport::Tgkill => Box::new(TgkillHandoff::new(first)),
+            Transport::EventFd => Box::new(EventFdHandoff::new(first)?),
+            Transport::Pipe => Box::new(PipeHandoff::new(first)?),
+            Transport::Futex => Box::new(FutexHandoff::new(first)),
+            Transport::Condvar => Box::new(CondvarHandoff::new(first)),
+            Transport::Process => Box::new(ProcessHandoff::new(first)?),
+        })
+    }
+}
+
+// where the signal transports start each side, set once when either is installed
+static TURNS: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+// the side the signal in flight is for
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+// each player's own stack. a turn can render a whole board, so this is a spawned thread's default
+const PLAYER_STACK: usize = 2 * 1024 * 1024;
+// CONTEXTS slot of the thread that served, switched away from for good by the first handoff
+const SERVER: usize = 2;
+// ping's and pong's contexts by Side::index, then the server's. leaked by SignalHandoff::install,
+// they are needed for as long as the process plays
+static CONTEXTS: [AtomicPtr<Context>; 3] = [const { AtomicPtr::new(std::ptr::null_mut()) }; 3];
+// the CONTEXTS slot running right now
+static RUNNING: AtomicUsize = AtomicUsize::new(SERVER);
+
+// the first switch to a player lands here, on the player's own stack
+extern "C" fn start_turns() {
+    let turn = TURNS[RUNNING.load(Ordering::SeqCst)].load(Ordering::SeqCst);
+    let turn: fn() = unsafe { std::mem::transmute(turn) };
+    turn();
+    unreachable!("a player's turns never end");
+}
+
+// SIGUSR1 was sent by give on this very thread, so the switch happens at a known point and the
+// handler's frame waits on the old player's stack until its turn comes round again. a stray
+// SIGUSR1 switches the running player to itself, which changes nothing
+extern "C" fn switch_turn(_sig: c_int) {
+    let next = NEXT.load(Ordering::SeqCst);
+    let running = RUNNING.swap(next, Ordering::SeqCst);
+    let from = CONTEXTS[running].load(Ordering::SeqCst);
+    let to = CONTEXTS[next].load(Ordering::SeqCst);
+    // swapcontext only fails on a bad signal mask, and these were all saved by glibc
+    let _ = unsafe { Context::switch(from, to) };
+}
+
+// SIGUSR1 to ourselves, whose handler switches from the player giving the turn to the one taking
+// it. each has a stack of its own and picks up where it left off, so turns return like on the
+// threaded transports
+pub struct SignalHandoff;
+
+impl SignalHandoff {
+    fn install(turns: [fn(); 2]) -> io::Result<SignalHandoff> {
+        for (slot, turn) in TURNS.iter().zip(turns) {
+            slot.store(turn as *const c_void as usize, Ordering::SeqCst);
+        }
+        for side in [Side::Ping, Side::Pong] {
+            let context = Context::new(start_turns, PLAYER_STACK)?;
+            CONTEXTS[side.index()].store(Box::into_raw(context), Ordering::SeqCst);
+        }
+        CONTEXTS[SERVER].store(Box::into_raw(Context::saved()), Ordering::SeqCst);
+
+        let mut act: sigaction = unsafe { std::mem::zeroed() };
+        act.sa_sigaction = switch_turn as *const () as usize;
+        unsafe {
+            sigemptyset(&mut act.sa_mask);
+            if sigaction(SIGUSR1, &act, std::ptr::null_mut()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(SignalHandoff)
+    }
+}
+
+impl Handoff for SignalHandoff {
+    fn give(&self, to: Side) {
+        NEXT.store(to.index(), Ordering::SeqCst);
+        // to this thread alone, the one whose context is saved for the switch. a signal to the
+        // whole process could land on any thread that has it unblocked, and kill(0, ..) would
+        // also hit a script that started us in its process group. back here once the turn
+        // comes round again
+        unsafe {
+            raise(SIGUSR1);
+        }
+    }
+
+    // a player is only ever switched to on its own turn
+    fn wait(&self, _side: Side) {}
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn classic(turns: [fn(); 2]) -> io::Result<Box<dyn Handoff>> {
+    Ok(Box::new(ClassicHandoff::install(turns)))
+}
+
+#[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+fn classic(_turns: [fn(); 2]) -> io::Result<Box<dyn Handoff>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the classic handoff rewrites x86_64 registers, use signal instead",
+    ))
+}
+
+// stack pointer at the first handoff. the turns never return, so every later one starts from
+// here too instead of piling a frame onto the stack per handoff until it overflows
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+static HANDOFF_STACK: AtomicI64 = AtomicI64::new(0);
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+unsafe extern "C" fn handle_signal(_sig: c_int, _info: *mut siginfo_t, ucontext: *mut ucontext_t) {
+    let target = TURNS[NEXT.load(Ordering::SeqCst)].load(Ordering::SeqCst);
+    let gregs = unsafe { &mut (*ucontext).uc_mcontext.gregs };
+    let stack = match HANDOFF_STACK.load(Ordering::SeqCst) {
+        0 => {
+            HANDOFF_STACK.store(gregs[REG_RSP as usize], Ordering::SeqCst);
+            gregs[REG_RSP as usize]
+        }
+        stack => stack,
+    };
+    gregs[REG_RSP as usize] = stack;
+    gregs[REG_RIP as usize] = target as i64;
+}
+
+// the original: a process-directed SIGUSR1 whose handler sends the thread into the other turn.
+// both players share whatever stack the first handoff was made on
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub struct ClassicHandoff;
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+impl ClassicHandoff {
+    fn install(turns: [fn(); 2]) -> ClassicHandoff {
+        for (slot, turn) in TURNS.iter().zip(turns) {
+            slot.store(turn as *const c_void as usize, Ordering::SeqCst);
+        }
+
+        let mut act: sigaction = unsafe { std::mem::zeroed() };
+        act.sa_sigaction = handle_signal as *const () as usize;
+        act.sa_flags = SA_SIGINFO;
+        unsafe {
+            sigemptyset(&mut act.sa_mask);
+            sigaction(SIGUSR1, &act, std::ptr::null_mut());
+        }
+        ClassicHandoff
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+impl Handoff for ClassicHandoff {
+    fn give(&self, to: Side) {
+        NEXT.store(to.index(), Ordering::SeqCst);
+        unsafe {
+            kill(getpid(), SIGUSR1);
+        }
+        // the handler has jumped away long before this
+        loop {
+            sleep(Duration::from_secs(1));
+        }
+    }
+
+    // the trampoline only ever enters a turn that is already ours
+    fn wait(&self, _side: Side) {}
+}
+
+// reads and writes on the fds below are retried when a signal like SIGWINCH interrupts them
+fn retry(mut call: impl FnMut() -> isize) {
+    loop {
+        if call() >= 0 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            return;
+        }
+    }
+}
+
+// an eventfd per side, written to hand it the turn
+pub struct EventFdHandoff {
+    fds: [c_int; 2],
+}
+
+impl EventFdHandoff {
+    fn new(first: Side) -> io::Result<EventFdHandoff> {
+        let mut fds = [-1; 2];
+        for fd in &mut fds {
+            *fd = unsafe { eventfd(0, EFD_CLOEXEC) };
+            if *fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let handoff = EventFdHandoff { fds };
+        handoff.give(first);
+        Ok(handoff)
+    }
+}
+
+impl Handoff for EventFdHandoff {
+    fn give(&self, to: Side) {
+        fence(Ordering::SeqCst);
+        let one: u64 = 1;
+        retry(|| unsafe { write(self.fds[to.index()], (&one as *const u64).cast(), 8) });
+    }
+
+    fn wait(&self, side: Side) {
+        let mut count: u64 = 0;
+        retry(|| unsafe { read(self.fds[side.index()], (&mut count as *mut u64).cast(), 8) });
+        fence(Ordering::SeqCst);
+    }
+}
+
+impl Drop for EventFdHandoff {
+    fn drop(&mut self) {
+        for fd in self.fds {
+            unsafe { close(fd) };
+        }
+    }
+}
+
+// a pipe per side, a byte written to hand it the turn
+pub struct PipeHandoff {
+    // read and write ends, indexed by Side::index
+    pipes: [[c_int; 2]; 2],
+}
+
+impl PipeHandoff {
+    fn new(first: Side) -> io::Result<PipeHandoff> {
+        let mut pipes = [[-1; 2]; 2];
+        for pipe in &mut pipes {
+            if unsafe { pipe2(pipe.as_mut_ptr(), O_CLOEXEC) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let handoff = PipeHandoff { pipes };
+        handoff.give(first);
+        Ok(handoff)
+    }
+}
+
+impl Handoff for PipeHandoff {
+    fn give(&self, to: Side) {
+        fence(Ordering::SeqCst);
+        retry(|| unsafe { write(self.pipes[to.index()][1], b"x".as_ptr().cast(), 1) });
+    }
+
+    fn wait(&self, side: Side) {
+        let mut byte = 0u8;
+        retry(|| unsafe {
+            read(
+                self.pipes[side.index()][0],
+                (&mut byte as *mut u8).cast(),
+                1,
+            )
+        });
+        fence(Ordering::SeqCst);
+    }
+}
+
+impl Drop for PipeHandoff {
+    fn drop(&mut self) {
+        for fd in self.pipes.as_flattened() {
+            unsafe { close(*fd) };
+        }
+    }
+}
+
+// the side holding the turn in one word, slept on with futex(2)
+pub struct FutexHandoff {
+    turn: AtomicU32,
+}
+
+impl FutexHandoff {
+    fn new(first: Side) -> FutexHandoff {
+        FutexHandoff {
+            turn: AtomicU32::new(first.index() as u32),
+        }
+    }
+}
+
+impl Handoff for FutexHandoff {
+    fn give(&self, to: Side) {
+        self.turn.store(to.index() as u32, Ordering::Release);
+        unsafe {
+            syscall(
+                SYS_futex,
+                self.turn.as_ptr(),
+                FUTEX_WAKE | FUTEX_PRIVATE_FLAG,
+                1,
+            );
+        }
+    }
+
+    fn wait(&self, side: Side) {
+        loop {
+            let turn = self.turn.load(Ordering::Acquire);
+            if turn == side.index() as u32 {
+                return;
+            }
+            // returns straight away if the turn moved on since the load
+            unsafe {
+                syscall(
+                    SYS_futex,
+                    self.turn.as_ptr(),
+                    FUTEX_WAIT | FUTEX_PRIVATE_FLAG,
+                    turn,
+                    std::ptr::null::<libc::timespec>(),
+                );
+            }
+        }
+    }
+}
+
+// the side holding the turn behind a mutex, with a condvar to wait on it
+pub struct CondvarHandoff {
+    turn: Mutex<Side>,
+    changed: Condvar,
+}
+
+impl CondvarHandoff {
+    fn new(first: Side) -> CondvarHandoff {
+        CondvarHandoff {
+            turn: Mutex::new(first),
+            changed: Condvar::new(),
+        }
+    }
+}
+
+impl Handoff for CondvarHandoff {
+    fn give(&self, to: Side) {
+        *self.turn.lock().unwrap_or_else(|err| err.into_inner()) = to;
+        self.changed.notify_one();
+    }
+
+    fn wait(&self, side: Side) {
+        let turn = self.turn.lock().unwrap_or_else(|err| err.into_inner());
+        let _turn = self
+            .changed
+            .wait_while(turn, |turn| *turn != side)
+            .unwrap_or_else(|err| err.into_inner());
+    }
+}
+
+// what the ping and pong processes both see: the side holding the turn and their pids, 0 until
+// a player has entered
+struct Turns {
+    turn: AtomicU32,
+    pids: [AtomicI32; 2],
+}
+
+// two processes, each asleep in sigwaitinfo until the other sends it SIGUSR1 with kill(2).
+// only the peer gets the signal, not the process group like kill(0, ..) would
+pub struct ProcessHandoff {
+    turns: Shared<Turns>,
+}
+
+impl ProcessHandoff {
+    // blocks SIGUSR1 in the calling process, which forks the players afterwards. they inherit
+    // the mask, so a turn handed over before its player waits stays pending instead of
+    // killing it
+    fn new(first: Side) -> io::Result<ProcessHandoff> {
+        let turns = Shared::new(Turns {
+            turn: AtomicU32::new(first.index() as u32),
+            pids: [AtomicI32::new(0), AtomicI32::new(0)],
+        })?;
+        block_usr1()?;
+        Ok(ProcessHandoff { turns })
+    }
+}
+
+fn usr1() -> sigset_t {
+    let mut set: sigset_t = unsafe { std::mem::zeroed() };
+    unsafe {
+        sigemptyset(&mut set);
+        sigaddset(&mut set, SIGUSR1);
+    }
+    set
+}
+
+// adds SIGUSR1 to the calling thread's mask, and to that of every thread it starts afterwards
+pub fn block_usr1() -> io::Result<()> {
+    match unsafe { pthread_sigmask(SIG_BLOCK, &usr1(), std::ptr::null_mut()) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err)),
+    }
+}
+
+// sleeps until SIGUSR1 is pending for the calling thread or the process and takes it. it has
+// to be blocked, or it would be delivered instead
+pub fn take_usr1() {
+    unsafe {
+        sigwaitinfo(&usr1(), std::ptr::null_mut());
+    }
+}
+
+impl Handoff for ProcessHandoff {
+    // a player that has not entered yet is not signalled. it finds the turn on its first wait:
+    // either it stored its pid before the load below or it loads the turn after the store
+    fn give(&self, to: Side) {
+        self.turns.turn.store(to.index() as u32, Ordering::SeqCst);
+        let pid = self.turns.pids[to.index()].load(Ordering::SeqCst);
+        if pid != 0 {
+            unsafe {
+                kill(pid, SIGUSR1);
+            }
+        }
+    }
+
+    // a signal that arrives between the check and sigwaitinfo stays pending, so none is missed
+    fn wait(&self, side: Side) {
+        while self.turns.turn.load(Ordering::SeqCst) != side.index() as u32 {
+            take_usr1();
+        }
+    }
+
+    fn enter(&self, side: Side) {
+        self.turns.pids[side.index()].store(unsafe { getpid() }, Ordering::SeqCst);
+    }
+}
+
+// two threads of one process, each asleep in sigwaitinfo until the other sends SIGUSR1 to its
+// thread id with tgkill(2). where flip and flop's kill goes to the whole process, to be handled
+// by whichever thread does not block it, this reaches exactly the thread whose turn it is.
+// and where the trampoline takes the signal itself to mean "your turn", here it only means
+// "look at the turn": signals of one kind pending together merge into one and a stray one can
+// come from anywhere, so it is the turn word that decides and neither a merged nor a stray
+// signal can lose or repeat a turn
+pub struct TgkillHandoff {
+    turn: AtomicU32,
+    // kernel thread ids, 0 until a player has entered
+    tids: [AtomicI32; 2],
+}
+
+impl TgkillHandoff {
+    fn new(first: Side) -> TgkillHandoff {
+        TgkillHandoff {
+            turn: AtomicU32::new(first.index() as u32),
+            tids: [AtomicI32::new(0), AtomicI32::new(0)],
+        }
+    }
+}
+
+impl Handoff for TgkillHandoff {
+    // as with processes, a player that has not entered yet finds the turn on its first wait
+    fn give(&self, to: Side) {
+        self.turn.store(to.index() as u32, Ordering::SeqCst);
+        let tid = self.tids[to.index()].load(Ordering::SeqCst);
+        if tid != 0 {
+            unsafe {
+                syscall(SYS_tgkill, getpid(), tid, SIGUSR1);
+            }
+        }
+    }
+
+    fn wait(&self, side: Side) {
+        while self.turn.load(Ordering::SeqCst) != side.index() as u32 {
+            take_usr1();
+        }
+    }
+
+    // the mask is per thread: each player blocks SIGUSR1 for itself before it can be sent one
+    fn enter(&self, side: Side) {
+        block_usr1().expect("SIGUSR1 is a valid signal to block");
+        let tid = unsafe { syscall(SYS_gettid) } as i32;
+        self.tids[side.index()].store(tid, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    const TURNS_EACH: usize = 300;
+
+    // ping and pong on threads of their own, each taking TURNS_EACH turns. returns who played
+    // in what order, and how often a turn started while the other was still in its own
+    fn play_turns(transport: Transport) -> (Vec<Side>, u32) {
+        let handoff = transport
+            .create(Side::Ping, [|| {}; 2])
+            .expect("the transport sets up");
+        let played = Mutex::new(Vec::new());
+        let playing = AtomicBool::new(false);
+        let overlapping = AtomicU32::new(0);
+
+        thread::scope(|scope| {
+            for side in [Side::Ping, Side::Pong] {
+                let (handoff, played, playing, overlapping) =
+                    (&*handoff, &played, &playing, &overlapping);
+                scope.spawn(move || {
+                    handoff.enter(side);
+                    for turn in 0..TURNS_EACH {
+                        handoff.wait(side);
+                        if playing.swap(true, Ordering::SeqCst) {
+                            overlapping.fetch_add(1, Ordering::SeqCst);
+                        }
+                        played.lock().expect("no player panics").push(side);
+                        thread::yield_now();
+                        playing.store(false, Ordering::SeqCst);
+                        // pong's last turn ends the run, there is nobody left to give it to
+                        if side == Side::Ping || turn + 1 < TURNS_EACH {
+                            handoff.give(side.opponent());
+                        }
+                    }
+                });
+            }
+        });
+        (
+            played.into_inner().expect("no player panics"),
+            overlapping.into_inner(),
+        )
+    }
+
+    #[test]
+    fn every_threaded_transport_alternates_strictly() {
+        for transport in Transport::ALL
+            .into_iter()
+            .filter(|transport| transport.threaded())
+        {
+            let (played, overlapping) = play_turns(transport);
+            assert_eq!(played.len(), 2 * TURNS_EACH, "{}", transport.name());
+            for (n, side) in played.iter().enumerate() {
+                let expected = if n % 2 == 0 { Side::Ping } else { Side::Pong };
+                assert_eq!(*side, expected, "{} turn {}", transport.name(), n);
+            }
+            assert_eq!(overlapping, 0, "{}", transport.name());
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::controller::PaddleController;
+use crate::game::{GameState, Side};
+use crate::term::Key;
+
+// a terminal only reports presses, never releases, so each press buys a little travel.
+// holding a key lets autorepeat top it up, capped so the paddle stops soon after letting go
+const ROWS_PER_PRESS: f64 = 1.0;
+const MAX_PENDING_ROWS: f64 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Keys {
+    // w/s and the arrows both work
+    Any,
+    // w and s only
+    Letters,
+    Arrows,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Human {
+    keys: Keys,
+    // rows still to travel, negative is up
+    pending: f64,
+}
+
+impl Human {
+    pub fn new(keys: Keys) -> Human {
+        Human { keys, pending: 0.0 }
+    }
+}
+
+impl PaddleController for Human {
+    fn decide(&mut self, game: &GameState, side: Side) -> f64 {
+        let speed = game.skills[side.index()].paddle_speed;
+        let delta = self.pending.clamp(-speed, speed);
+        self.pending -= delta;
+        delta
+    }
+
+    fn press(&mut self, key: Key) {
+        let direction = match (key, self.keys) {
+            (Key::Char('w' | 'W'), Keys::Any | Keys::Letters) => -1.0,
+            (Key::Char('s' | 'S'), Keys::Any | Keys::Letters) => 1.0,
+            (Key::Up, Keys::Any | Keys::Arrows) => -1.0,
+            (Key::Down, Keys::Any | Keys::Arrows) => 1.0,
+            _ => return,
+        };
+
+        // reversing drops whatever travel was left the other way
+        if self.pending * direction < 0.0 {
+            self.pending = 0.0;
+        }
+        self.pending =
+            (self.pending + direction * ROWS_PER_PRESS).clamp(-MAX_PENDING_ROWS, MAX_PENDING_ROWS);
+    }
+
+    // forget presses made while the game was not listening
+    fn reset(&mut self) {
+        self.pending = 0.0;
+    }
+
+    fn is_human(&self) -> bool {
+        true
+    }
+}
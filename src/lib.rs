@@ -0,0 +1,73 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub mod bench;
+pub mod boxscore;
+pub mod config;
+pub mod context;
+pub mod controller;
+pub mod court;
+pub mod game;
+pub mod handoff;
+pub mod human;
+pub mod observer;
+pub mod physics;
+pub mod render;
+pub mod replay;
+pub mod screen;
+pub mod shared;
+pub mod sim;
+pub mod skill;
+pub mod stress;
+pub mod term;
+pub mod viewer;
+
+pub use config::Config;
+pub use court::Court;
+pub use game::{End, Event, GameState, Inputs, Phase, PointReason, Rules, Side, step};
+
+// the court used when nothing else is asked for or the terminal size is unknown
+pub const WIDTH: usize = 76;
+pub const HEIGHT: usize = 20;
+
+// base speed
+pub const BASE_FRAME_DELAY: u64 = 80;
+pub const FRAME_VARIATION: u64 = 40;
+
+// some physics
+pub const MAX_ALLOWED_SPEED: f64 = 10.0;
+pub const MIN_ALLOWED_SPEED: f64 = 5.5;
+pub const PADDLE_SIZE: f64 = 3.0;
+// rand misses
+pub const MISS_PROBABILITY_BASE: f64 = 0.15;
+pub const DIFFICULTY_SCALING: f64 = 0.08;
+pub const MAX_SCORE: u32 = 11;
+
+// how long an animated frame stays on screen, in milliseconds
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timing {
+    pub frame_delay: u64,
+    // each frame is up to this much shorter or longer than frame_delay
+    pub frame_variation: u64,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing {
+            frame_delay: BASE_FRAME_DELAY,
+            frame_variation: FRAME_VARIATION,
+        }
+    }
+}
+
+pub fn random_frame_delay(game: &mut GameState) -> Duration {
+    let min = game
+        .timing
+        .frame_delay
+        .saturating_sub(game.timing.frame_variation);
+    let max = game.timing.frame_delay + game.timing.frame_variation;
+    let delay_ms = game.delay_rng.random_range(min..=max);
+    Duration::from_millis(delay_ms)
+}
@@ -1,697 +1,908 @@
+mod cli;
+
+use cli::{Command, Format, Options};
 use libc::{
-    c_int, c_void, kill, sigaction, sigemptyset, siginfo_t, ucontext_t, SA_SIGINFO, SIGUSR1,
+    _exit, PR_SET_PDEATHSIG, SIG_IGN, SIGINT, SIGKILL, STDIN_FILENO, STDOUT_FILENO, WEXITSTATUS,
+    WIFEXITED, WIFSIGNALED, WTERMSIG, c_int, fork, getpid, getppid, isatty, kill, pid_t, prctl,
+    signal, waitpid, write,
 };
-use rand::Rng;
-use std::thread::sleep;
-use std::time::Duration;
-
-const WIDTH: usize = 76;
-const HEIGHT: usize = 20;
-
-// base speed
-const BASE_FRAME_DELAY: u64 = 80;
-const FRAME_VARIATION: u64 = 40;
-
-// some physics
-const MAX_ALLOWED_SPEED: f64 = 10.0;
-const MIN_ALLOWED_SPEED: f64 = 5.5;
-const PADDLE_CENTER: f64 = (HEIGHT / 2) as f64;
-const PADDLE_SIZE: f64 = 3.0;
-// rand misses
-const MISS_PROBABILITY_BASE: f64 = 0.15;
-const DIFFICULTY_SCALING: f64 = 0.08;
-const MAX_SCORE: u32 = 11;
-const NET_POSITION: usize = WIDTH / 2;
-
-const STATE_SERVE: u8 = 0;
-const STATE_RALLY: u8 = 1;
-const STATE_POINT_END: u8 = 2;
-
-static mut BALL_X: f64 = 1.0;
-static mut BALL_Y: f64 = (HEIGHT / 2) as f64;
-static mut BALL_DX: f64 = 1.0;
-static mut BALL_DY: f64 = 0.0;
-// affects trajectory (flugbahn) and bounce behavior
-static mut BALL_SPIN: f64 = 0.0;
-
-static mut PADDLE_LEFT_Y: f64 = (HEIGHT / 2) as f64;
-static mut PADDLE_RIGHT_Y: f64 = (HEIGHT / 2) as f64;
-
-static mut SCORE_PING: u32 = 0;
-static mut SCORE_PONG: u32 = 0;
-static mut RALLY_LENGTH: u32 = 0;
-static mut LONGEST_RALLY: u32 = 0;
-static mut GAME_STATE: u8 = STATE_SERVE;
-// 0 = ping, 1 = pong
-static mut SERVING_PLAYER: usize = 0; 
-static mut GAME_OVER: bool = false;
-
-unsafe extern "C" fn handle_signal(
-    _sig: c_int,
-    _info: *mut siginfo_t,
-    ucontext: *mut ucontext_t,
-) {
-    static mut A: u64 = 0;
-    let ret = if A % 2 == 0 { flop } else { flip } as *const c_void as i64;
-    A += 1;
-    (*ucontext).uc_mcontext.gregs[16] = ret;
-}
-
-fn draw_board(current_player: &str) {
-    print!("\x1B[2J\x1B[H");
-    
-    let bx;
-    let by;
-    let left_paddle_y;
-    let right_paddle_y;
-    let score_ping;
-    let score_pong;
-    let serving;
-    let game_state;
-    let rally_length;
-    let longest_rally;
-    let game_over;
-    let ball_dx;
-    let ball_dy;
-    let ball_speed;
-    
-    unsafe {
-        bx = BALL_X.round() as usize;
-        by = BALL_Y.round() as usize;
-        left_paddle_y = PADDLE_LEFT_Y.round() as usize;
-        right_paddle_y = PADDLE_RIGHT_Y.round() as usize;
-        score_ping = SCORE_PING;
-        score_pong = SCORE_PONG;
-        serving = SERVING_PLAYER;
-        game_state = GAME_STATE;
-        rally_length = RALLY_LENGTH;
-        longest_rally = LONGEST_RALLY;
-        game_over = GAME_OVER;
-        ball_dx = BALL_DX;
-        ball_dy = BALL_DY;
-        ball_speed = (BALL_DX * BALL_DX + BALL_DY * BALL_DY).sqrt();
-    }
-    
-    println!("---------------------- alessandrods nerd snippet ----------------------");
-    println!("ping: {:<2}  pong: {:<2}  │  current: {:<4}  │ serving: {:<4} │ rally: {:<3}", 
-             score_ping, score_pong, current_player, 
-             if serving == 0 { "ping" } else { "pong" }, rally_length);
-    println!("║ ball speed: {:.2} │ longest rally: {:<3} │ {:<24}", 
-             ball_speed, longest_rally, 
-             if game_over { "game over!" } else { match game_state {
-                 STATE_SERVE => "serving...",
-                 STATE_RALLY => "in progress",
-                 STATE_POINT_END => "point ended",
-                 _ => "",
-             }});
-    
-    let direction = if ball_dx > 0.0 { "→" } else if ball_dx < 0.0 { "←" } else { "-" };
-    let vert_dir = if ball_dy > 0.0 { "↓" } else if ball_dy < 0.0 { "↑" } else { "-" };
-    println!("ball direction: {}{} │ position: ({},{}) │ ball in {:?} side ", 
-             direction, vert_dir, bx, by,
-             if bx < NET_POSITION { "pings" } else { "pongs" });
-    println!("----------------------------------------------------------------------");
-    
-    println!("------------------------------------------------------------------------------");
-    
-    let ping_display_width = WIDTH / 2 - 1;
-    let pong_display_width = WIDTH / 2 - 1;
-    
-    let ball_in_bounds = bx >= 0 && bx < WIDTH && by >= 0 && by < HEIGHT;
-    
-    for y in 0..HEIGHT {
-        print!("║");
-        for x in 0..WIDTH {
-            if x == 0 && y >= left_paddle_y - 1 && y <= left_paddle_y + 1 {
-                print!("▌");
-            } else if x == WIDTH - 1 && y >= right_paddle_y - 1 && y <= right_paddle_y + 1 {
-                print!("▐");
-            } else if x == NET_POSITION {
-                print!("│");
-            } else if ball_in_bounds && x == bx && y == by {
-                print!("●");
-            } else {
-                print!(" ");
+use serde::Serialize;
+use sig_ping_pong::bench::{BenchReport, Comparison, Latencies, WARMUP_HANDOFFS, system};
+use sig_ping_pong::boxscore::BoxScore;
+use sig_ping_pong::controller::{ControllerKind, Controllers, build_controllers, decide};
+use sig_ping_pong::handoff::{Handoff, Transport};
+use sig_ping_pong::observer::{EventLog, Observer, notify};
+use sig_ping_pong::render::{describe_event, draw_board};
+use sig_ping_pong::replay::{Recorder, Replay};
+use sig_ping_pong::screen::Screen;
+use sig_ping_pong::shared::Shared;
+use sig_ping_pong::sim::{match_seed, run_batch};
+use sig_ping_pong::stress;
+use sig_ping_pong::term::{self, AlternateScreen, Key, RawMode, read_keys, terminal_size};
+use sig_ping_pong::viewer;
+use sig_ping_pong::{Config, Court, End, Event, GameState, Phase, Side, random_frame_delay, step};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+// exit status once a match is decided: this plus the winner's Side::index
+const EXIT_WINNER_BASE: i32 = 10;
+// matches played when --matches is left out
+const DEFAULT_SIMULATED_MATCHES: u32 = 100;
+// handoffs made by bench and stress when --handoffs is left out
+const DEFAULT_HANDOFFS: u64 = 1_000_000;
+
+// the game is shared with the other player's process under --transport process
+const _: () = assert!(
+    !std::mem::needs_drop::<GameState>(),
+    "a game in shared memory cannot own heap memory"
+);
+
+struct App {
+    // in shared memory, for every transport alike
+    game: Shared<GameState>,
+    config: Config,
+    recorder: Option<Recorder<BufWriter<File>>>,
+    observers: Vec<Box<dyn Observer>>,
+    // the current match's, printed and saved when the game quits. none when ping and pong are
+    // processes of their own, as each only sees its own turns
+    box_score: Option<BoxScore>,
+    // --stats-json and --stats-csv
+    stats_files: [Option<String>; 2],
+    // what the controllers were built from, so a rematch can build them again
+    kinds: [ControllerKind; 2],
+    controllers: Controllers,
+    // held for the whole match, flip and flop never return to drop them
+    raw_mode: Option<RawMode>,
+    _alternate_screen: Option<AlternateScreen>,
+    screen: Screen,
+    // show the renderer's byte counts under the board
+    render_stats: bool,
+    // the court follows the terminal size. off when --court pins it or the match is recorded
+    fit_court: bool,
+    // lines are cut here too when the terminal is narrower than the board
+    columns: usize,
+    format: Format,
+    // set for the bench command, which skips everything but the handoffs. shared like the game
+    bench: Option<Shared<Bench>>,
+}
+
+// a bench run: matches played back to back through flip and flop with nothing drawn,
+// timing every handoff
+struct Bench {
+    transport: Transport,
+    seed: u64,
+    // handoffs to time, after the warmup
+    handoffs: u64,
+    bare: bool,
+    // handoffs so far, warmup included
+    made: u64,
+    matches: u32,
+    frames: u64,
+    // when the handoff in flight was started
+    sent: Option<Instant>,
+    latencies: Latencies,
+    // the end of the warmup
+    started: Instant,
+}
+
+impl Bench {
+    // the receiving side is running: times the handoff that got it here
+    fn arrived(&mut self, format: Format) {
+        let Some(sent) = self.sent.take() else {
+            return;
+        };
+        let latency = sent.elapsed();
+        self.made += 1;
+        if self.made <= WARMUP_HANDOFFS {
+            if self.made == WARMUP_HANDOFFS {
+                self.matches = 0;
+                self.frames = 0;
+                self.started = Instant::now();
             }
+            return;
+        }
+
+        self.latencies.record(latency);
+        if self.latencies.len() == self.handoffs {
+            self.finish(format);
         }
-        println!("║");
     }
-    
-    println!("------------------------------------------------------------------------------");
 
-    if game_over {
-        let winner = if score_ping >= MAX_SCORE { "ping" } else { "pong" };
-        println!("game is game. winner is {}", winner);
-        println!("final score: ping {} - {} pong", score_ping, score_pong);
-        println!("longest rally: {} hits", longest_rally);
+    fn finish(&self, format: Format) -> ! {
+        let seconds = self.started.elapsed().as_secs_f64();
+        let report = BenchReport {
+            system: system(),
+            transport: self.transport.name().to_string(),
+            seed: self.seed,
+            bare: self.bare,
+            handoffs: self.latencies.len(),
+            matches: self.matches,
+            frames: self.frames,
+            seconds,
+            handoffs_per_second: self.latencies.len() as f64 / seconds,
+            latency: self.latencies.report(),
+        };
+        match format {
+            Format::Text => println!("{}", report),
+            Format::Json => println!("{}", to_json(&report)),
+        }
+        std::process::exit(0);
     }
 }
 
-fn visualize_point_end(winner: usize, is_miss: bool, current_player: &str) {
-    unsafe {
-        draw_board(current_player);
-        
-        if is_miss {
-            if (current_player == "ping" && winner == 1) || (current_player == "pong" && winner == 0) {
-                println!("{} missed the ball. point to {}!", 
-                        current_player, 
-                        if winner == 0 { "ping" } else { "pong" });
-            } else {
-                println!("point to {} opponent missed the ball.", 
-                        if winner == 0 { "ping" } else { "pong" });
-            }
-        } else if BALL_X < 0.0 {
-            println!("ball went out on pings side. point to pong!");
-        } else if BALL_X >= WIDTH as f64 {
-            println!("ball went out on pongs side. point to ping!");
-        } else {
-            println!("point to {}!", if winner == 0 { "ping" } else { "pong" });
-        }
-        
-        sleep(Duration::from_millis(1000));
-        
-        score_point(winner);
-        
-        draw_board(current_player);
-        let score_ping = SCORE_PING;
-        let score_pong = SCORE_PONG;
-        println!("score: ping {} - {} pong", score_ping, score_pong);
-        
-        sleep(Duration::from_millis(1000));
-        reset_for_serve(SERVING_PLAYER);
-    }
-}
-
-fn ensure_minimum_ball_speed() {
-    unsafe {
-        let current_speed = (BALL_DX * BALL_DX + BALL_DY * BALL_DY).sqrt();
-        
-        if current_speed < MIN_ALLOWED_SPEED {
-            let (norm_dx, norm_dy) = if current_speed > 0.0 {
-                (BALL_DX / current_speed, BALL_DY / current_speed)
-            } else {
-   
-                let direction = if BALL_X < (WIDTH as f64 / 2.0) { 1.0 } else { -1.0 };
-                (direction, rand::rng().random_range(-0.5..0.5))
-            };
-            
-            BALL_DX = norm_dx * MIN_ALLOWED_SPEED;
-            BALL_DY = norm_dy * MIN_ALLOWED_SPEED;
-            
-            if BALL_DX.abs() < 0.1 {
-                let direction = if BALL_X < (WIDTH as f64 / 2.0) { 1.0 } else { -1.0 };
-                BALL_DX = direction * MIN_ALLOWED_SPEED * 0.8;
-                BALL_DY = if BALL_DY == 0.0 { 
-                    rand::rng().random_range(-0.3..0.3) 
-                } else { 
-                    BALL_DY 
-                };
-            }
+// how a decided or abandoned match is reported by --format json
+#[derive(Serialize)]
+struct MatchResult<'a> {
+    seed: u64,
+    court: Court,
+    score: [u32; 2],
+    games: [u32; 2],
+    winner: Option<Side>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    box_score: Option<&'a BoxScore>,
+}
+
+// flip and flop reach the app from wherever their turn runs: after a rip rewrite, on a context
+// of their own or on their own thread. the side holding the turn holds the lock until it hands
+// over, so the other side never sees the app mid-turn even if a transport misbehaves
+static APP: OnceLock<Mutex<App>> = OnceLock::new();
+
+fn app() -> MutexGuard<'static, App> {
+    APP.get()
+        .expect("set before the first turn")
+        .lock()
+        // a panic exits through the hook, nothing is left to care about a half-played turn
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+// the score as the signal handler reads it, which can neither lock nor allocate. in shared
+// memory so the referee of a two-process match sees the players' points
+static SCORE: OnceLock<Shared<[AtomicU32; 2]>> = OnceLock::new();
+
+// called after every step that can score
+fn publish_score(game: &GameState) {
+    if let Some(score) = SCORE.get() {
+        for (published, points) in score.iter().zip(game.score) {
+            published.store(points, Ordering::Relaxed);
         }
     }
 }
 
-fn update_paddles() {
-    unsafe {
-        let target_y = if BALL_DX < 0.0 && BALL_X < WIDTH as f64 / 2.0 {
-            let time_to_reach = if BALL_DX != 0.0 { BALL_X / -BALL_DX } else { 0.0 };
-            let predicted_y = BALL_Y + BALL_DY * time_to_reach;
-            
-            predicted_y + rand::rng().random_range(-1.0..1.0)
-        } else {
-            PADDLE_CENTER + rand::rng().random_range(-1.0..1.0)
-        };
-        
-        let paddle_speed = 0.5;
-        if (PADDLE_LEFT_Y - target_y).abs() > 0.1 {
-            if PADDLE_LEFT_Y < target_y {
-                PADDLE_LEFT_Y += paddle_speed;
-            } else {
-                PADDLE_LEFT_Y -= paddle_speed;
-            }
-        }
-        
-        let target_y = if BALL_DX > 0.0 && BALL_X > WIDTH as f64 / 2.0 {
-            let time_to_reach = if BALL_DX != 0.0 { (WIDTH as f64 - BALL_X) / BALL_DX } else { 0.0 };
-            let predicted_y = BALL_Y + BALL_DY * time_to_reach;
-            
-            predicted_y + rand::rng().random_range(-1.0..1.0)
-        } else {
-            PADDLE_CENTER + rand::rng().random_range(-1.0..1.0)
-        };
-        
-        if (PADDLE_RIGHT_Y - target_y).abs() > 0.1 {
-            if PADDLE_RIGHT_Y < target_y {
-                PADDLE_RIGHT_Y += paddle_speed;
-            } else {
-                PADDLE_RIGHT_Y -= paddle_speed;
+// how flip and flop pass the turn, picked by --transport
+static HANDOFF: OnceLock<Box<dyn Handoff>> = OnceLock::new();
+
+fn handoff() -> &'static dyn Handoff {
+    HANDOFF.get().expect("set before the first turn").as_ref()
+}
+
+// "final score: ping X - Y pong" without allocating or locking, for the signal handler
+fn write_final_score(score: [u32; 2]) {
+    let mut line = [0u8; 64];
+    let mut len = 0;
+    let mut push = |bytes: &[u8]| {
+        line[len..len + bytes.len()].copy_from_slice(bytes);
+        len += bytes.len();
+    };
+    let digits = |mut n: u32| {
+        let mut buf = [0u8; 10];
+        let mut start = buf.len();
+        loop {
+            start -= 1;
+            buf[start] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                break;
             }
         }
-        
-        PADDLE_LEFT_Y = PADDLE_LEFT_Y.max(PADDLE_SIZE).min(HEIGHT as f64 - PADDLE_SIZE);
-        PADDLE_RIGHT_Y = PADDLE_RIGHT_Y.max(PADDLE_SIZE).min(HEIGHT as f64 - PADDLE_SIZE);
+        (buf, start)
+    };
+
+    push(b"final score: ping ");
+    let (buf, start) = digits(score[0]);
+    push(&buf[start..]);
+    push(b" - ");
+    let (buf, start) = digits(score[1]);
+    push(&buf[start..]);
+    push(b" pong\n");
+
+    unsafe {
+        write(STDOUT_FILENO, line.as_ptr().cast(), len);
     }
 }
 
-fn random_frame_delay() -> Duration {
-    let min = BASE_FRAME_DELAY.saturating_sub(FRAME_VARIATION);
-    let max = BASE_FRAME_DELAY + FRAME_VARIATION;
-    let delay_ms = rand::rng().random_range(min..=max);
-    Duration::from_millis(delay_ms)
+extern "C" fn handle_quit(sig: c_int) {
+    term::restore();
+    if let Some(score) = SCORE.get() {
+        write_final_score(
+            score
+                .each_ref()
+                .map(|points| points.load(Ordering::Relaxed)),
+        );
+    }
+    unsafe { _exit(128 + sig) };
 }
 
-fn update_ball() {
-    unsafe {
-        BALL_DY += BALL_SPIN * 0.02;
-        
-        ensure_minimum_ball_speed();
-        
-        BALL_X += BALL_DX;
-        BALL_Y += BALL_DY;
-        
-        let started_on_left_side = BALL_X - BALL_DX < NET_POSITION as f64;
-        
-        if BALL_Y < 1.0 {
-            BALL_Y = 1.0;
-            BALL_DY = -BALL_DY * 0.95;
-            BALL_SPIN *= 0.7;
-            
-            ensure_minimum_ball_speed();
-        }
-        
-        if BALL_Y > (HEIGHT - 2) as f64 {
-            BALL_Y = (HEIGHT - 2) as f64;
-            BALL_DY = -BALL_DY * 0.95;
-            BALL_SPIN *= 0.7;
-            
-            ensure_minimum_ball_speed();
+// writes the --stats-json and --stats-csv files. a failure is reported, the other file is still tried
+fn save_box_score(app: &App) {
+    let Some(box_score) = &app.box_score else {
+        return;
+    };
+    let [json, csv] = &app.stats_files;
+    let save = |path: &Option<String>, write: &dyn Fn(&mut BufWriter<File>) -> io::Result<()>| {
+        let Some(path) = path else {
+            return;
+        };
+        let result = File::create(path).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            write(&mut out)?;
+            out.flush()
+        });
+        if let Err(err) = result {
+            eprintln!("cannot write box score to {}: {}", path, err);
         }
-        
-        static mut CONSECUTIVE_NET_HITS: u8 = 0;
-        
-        let very_close_to_net = BALL_X.round() as usize == NET_POSITION || 
-                                BALL_X.round() as usize == NET_POSITION + 1 ||
-                                BALL_X.round() as usize == NET_POSITION - 1;
-        
-        if very_close_to_net && 
-           BALL_Y > 1.0 && BALL_Y < (HEIGHT - 2) as f64 &&
-           CONSECUTIVE_NET_HITS < 1 && 
-           rand::rng().random_bool(0.15) {
-            
-            CONSECUTIVE_NET_HITS += 1;
-            
-            if rand::rng().random_bool(0.2) {
-                BALL_DX = -BALL_DX * 0.8;
-            } else {
-                BALL_DX = BALL_DX * 0.6;
-            }
-            
-            if BALL_DX.abs() < MIN_ALLOWED_SPEED {
-                BALL_DX = if BALL_DX < 0.0 { -MIN_ALLOWED_SPEED * 1.2 } else { MIN_ALLOWED_SPEED * 1.2 };
+    };
+    save(json, &|out| {
+        serde_json::to_writer_pretty(&mut *out, box_score)?;
+        writeln!(out)
+    });
+    save(csv, &|out| box_score.write_csv(out));
+}
+
+// back to the normal screen with the score left behind. exits with the winner's status
+// if the match was decided, 0 if it was abandoned
+fn quit(app: &mut App) -> ! {
+    term::restore();
+    finish_observers(&mut app.observers);
+    save_box_score(app);
+    // piped frames end mid-line, the results get lines of their own
+    if unsafe { isatty(STDOUT_FILENO) } != 1 {
+        println!();
+    }
+    match app.format {
+        Format::Text => {
+            println!(
+                "final score: ping {} - {} pong",
+                app.game.score[0], app.game.score[1]
+            );
+            if app.game.rules.best_of > 1 {
+                println!(
+                    "games: ping {} - {} pong",
+                    app.game.games[0], app.game.games[1]
+                );
             }
-            
-            if started_on_left_side {
-                BALL_X = (NET_POSITION + 2) as f64;
-                if BALL_DX < 0.0 && rand::rng().random_bool(0.7) {
-                    BALL_DX = -BALL_DX;
-                }
-            } else {
-                BALL_X = (NET_POSITION - 2) as f64;
-                if BALL_DX > 0.0 && rand::rng().random_bool(0.7) {
-                    BALL_DX = -BALL_DX;
-                }
+            if let Some(winner) = app.game.winner() {
+                println!("{} wins", winner);
             }
-            
-            BALL_DY += rand::rng().random_range(-0.2..0.2);
-            
-            ensure_minimum_ball_speed();
-        } else {
-            if !very_close_to_net {
-                CONSECUTIVE_NET_HITS = 0;
+            if let Some(box_score) = &app.box_score
+                && !box_score.points.is_empty()
+            {
+                println!("\n{}", box_score);
             }
         }
-        
-        if started_on_left_side && BALL_X > NET_POSITION as f64 && BALL_DX < 0.0 {
-            BALL_DX = -BALL_DX;
-        } else if !started_on_left_side && BALL_X < NET_POSITION as f64 && BALL_DX > 0.0 {
-            BALL_DX = -BALL_DX;
+        Format::Json => {
+            let result = MatchResult {
+                seed: app.game.seed,
+                court: app.game.court,
+                score: app.game.score,
+                games: app.game.games,
+                winner: app.game.winner(),
+                box_score: app.box_score.as_ref(),
+            };
+            println!("{}", to_json(&result));
         }
     }
+    if app.render_stats {
+        println!("{}", app.screen.stats());
+    }
+
+    let status = match app.game.winner() {
+        Some(winner) => EXIT_WINNER_BASE + winner.index() as i32,
+        None => 0,
+    };
+    std::process::exit(status);
 }
 
-fn handle_paddle_hit(is_left_paddle: bool) {
-    unsafe {
-        let paddle_y = if is_left_paddle { PADDLE_LEFT_Y } else { PADDLE_RIGHT_Y };
-        
-        let hit_pos = (BALL_Y - paddle_y) / PADDLE_SIZE;
-        
-        BALL_DX = -BALL_DX;
-        
-        let speed = (BALL_DX * BALL_DX + BALL_DY * BALL_DY).sqrt();
-        let new_speed = (speed * 1.05).min(MAX_ALLOWED_SPEED);
-        
-        BALL_DY += hit_pos * 0.8;
-        
-        BALL_SPIN = hit_pos * 1.5;
-        
-        let magnitude = (BALL_DX * BALL_DX + BALL_DY * BALL_DY).sqrt();
-        if magnitude > 0.0 {
-            BALL_DX = (BALL_DX / magnitude) * new_speed;
-            BALL_DY = (BALL_DY / magnitude) * new_speed;
-        } else {
-            BALL_DX = if is_left_paddle { 1.0 } else { -1.0 } * new_speed;
-            BALL_DY = rand::rng().random_range(-0.3..0.3);
-        }
-        
-        BALL_DY += rand::rng().random_range(-0.1..0.1);
-        
-        if is_left_paddle {
-            BALL_X = 3.0;
-        } else {
-            BALL_X = (WIDTH - 4) as f64;
-        }
-        
-        ensure_minimum_ball_speed();
-        
-        RALLY_LENGTH += 1;
+fn humans(app: &App) -> [bool; 2] {
+    [app.controllers[0].is_human(), app.controllers[1].is_human()]
+}
+
+// the board with `messages` underneath, sent as a diff against the last frame
+fn draw(app: &mut App, current_player: Side, messages: &[String]) {
+    let mut text = draw_board(&app.game, current_player);
+    for message in messages {
+        text.push_str(message);
+        text.push('\n');
+    }
+    match humans(app) {
+        [true, true] => text.push_str("ping [w/s] │ pong [↑/↓] │ [q] quit\n"),
+        [false, false] => {}
+        _ => text.push_str("[w/s] or [↑/↓] move │ [q] quit\n"),
     }
+    if app.render_stats {
+        text.push_str(&format!("{}\n", app.screen.stats()));
+    }
+
+    // nothing is drawn wider than the board's border, messages included
+    app.screen
+        .set_width((app.game.court.width + 2).min(app.columns));
+    // like print!, nothing sensible to do if the terminal went away
+    let _ = app.screen.draw(&mut io::stdout().lock(), &text);
 }
 
-fn calculate_miss_probability(paddle_y: f64) -> f64 {
-    unsafe {
-        let speed = (BALL_DX * BALL_DX + BALL_DY * BALL_DY).sqrt();
-        let distance_from_paddle = (BALL_Y - paddle_y).abs();
-        
-        let mut miss_prob = MISS_PROBABILITY_BASE;
-        
-        miss_prob += (speed - 1.0) * DIFFICULTY_SCALING;
-        
-        if distance_from_paddle > PADDLE_SIZE * 0.5 {
-            miss_prob += (distance_from_paddle - PADDLE_SIZE * 0.5) * 0.15;
+// after a SIGWINCH: refits the court if it follows the terminal and repaints from scratch.
+// returns whether anything happened
+fn handle_resize(app: &mut App) -> bool {
+    if !term::take_resize() {
+        return false;
+    }
+    if let Some((columns, rows)) = terminal_size() {
+        app.columns = columns;
+        // shrunk below the smallest court, the board keeps its size and is cut at the edge
+        if app.fit_court
+            && let Ok(court) = Court::fit(columns, rows)
+        {
+            app.game.resize(court);
         }
-        
-        miss_prob.min(0.95).max(0.05)
     }
+    app.screen.invalidate();
+    true
 }
 
-fn handle_potential_miss(is_left_paddle: bool) -> bool {
-    unsafe {
-        let paddle_y = if is_left_paddle { PADDLE_LEFT_Y } else { PADDLE_RIGHT_Y };
-        
-        let miss_prob = calculate_miss_probability(paddle_y);
-        
-        if rand::rng().random_bool(miss_prob) {
-
-            if is_left_paddle {
-                BALL_X = -1.0; 
-            } else {
-                BALL_X = WIDTH as f64 + 1.0; 
-            }
-            
-            let miss_offset = rand::rng().random_range(1.5..2.5);
-            if BALL_Y < paddle_y {
-                BALL_Y = (paddle_y - miss_offset).max(1.0);
-            } else {
-                BALL_Y = (paddle_y + miss_offset).min((HEIGHT - 2) as f64);
+// waits out `duration`, feeding any keys typed meanwhile to the human players
+fn pause(app: &mut App, duration: Duration) {
+    if humans(app) == [false, false] {
+        sleep(duration);
+        return;
+    }
+
+    let deadline = Instant::now() + duration;
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        // a broken stdin just leaves the humans standing still
+        for key in read_keys(deadline - now).unwrap_or_default() {
+            match key {
+                Key::Char('q') => quit(app),
+                Key::Escape => confirm_quit(app),
+                key => {
+                    for controller in &mut app.controllers {
+                        controller.press(key);
+                    }
+                }
             }
-            
-            return true;
         }
-        
-        false
     }
 }
 
-fn reset_for_serve(server: usize) {
-    unsafe {
-        GAME_STATE = STATE_SERVE;
-        SERVING_PLAYER = server;
-        RALLY_LENGTH = 0;
-        
-        if server == 0 {
-            BALL_X = 1.0;
-            BALL_Y = PADDLE_LEFT_Y;
-        } else {
-            BALL_X = (WIDTH - 2) as f64;
-            BALL_Y = PADDLE_RIGHT_Y;
+// escape is easy to hit by accident, so it asks before abandoning the match. play stands
+// still until the answer, anything but yes carries on
+fn confirm_quit(app: &mut App) {
+    let prompt = ["quit the match? [y/n]".to_string()];
+    let side = app.game.turn;
+    draw(app, side, &prompt);
+    loop {
+        if handle_resize(app) {
+            draw(app, side, &prompt);
         }
-        BALL_DX = 0.0;
-        BALL_DY = 0.0;
-        BALL_SPIN = 0.0;
-        
-        if SCORE_PING >= MAX_SCORE || SCORE_PONG >= MAX_SCORE {
-            GAME_OVER = true;
+        let Ok(keys) = read_keys(Duration::from_secs(3600)) else {
+            return;
+        };
+        match keys.first() {
+            Some(Key::Char('y' | 'Y' | 'q')) => quit(app),
+            Some(_) => return,
+            None => {}
         }
     }
 }
 
-fn score_point(winner: usize) {
-    unsafe {
-        if winner == 0 {
-            SCORE_PING += 1;
-        } else {
-            SCORE_PONG += 1;
-        }
-        
-        if RALLY_LENGTH > LONGEST_RALLY {
-            LONGEST_RALLY = RALLY_LENGTH;
-        }
-        
-        GAME_STATE = STATE_POINT_END;
-        
-        let total_points = SCORE_PING + SCORE_PONG;
-        if total_points % 2 == 0 {
-            SERVING_PLAYER = 1 - SERVING_PLAYER;
-        }
+fn visualize_point_end(app: &mut App, events: &[Event], current_player: Side) {
+    let messages: Vec<String> = events.iter().filter_map(describe_event).collect();
+    draw(app, current_player, &messages);
+
+    pause(app, Duration::from_millis(1000));
+
+    let score = format!(
+        "score: ping {} - {} pong",
+        app.game.score[0], app.game.score[1]
+    );
+    draw(app, current_player, &[score]);
+
+    pause(app, Duration::from_millis(1000));
+    for controller in &mut app.controllers {
+        controller.reset();
     }
 }
 
-fn flip() {
-    unsafe {
-        if GAME_OVER {
-            draw_board("ping");
-            sleep(Duration::from_millis(1000));
-            kill(0, SIGUSR1);
-            loop {
-                sleep(Duration::from_millis(BASE_FRAME_DELAY));
+fn record(app: &mut App, events: &[Event]) {
+    let Some(recorder) = app.recorder.as_mut() else {
+        return;
+    };
+
+    let result = if app.game.game_over {
+        let mut recorder = app.recorder.take().expect("recorder checked above");
+        recorder
+            .record(&app.game, events)
+            .and_then(|_| recorder.finish().map(drop))
+    } else {
+        recorder.record(&app.game, events)
+    };
+
+    if let Err(err) = result {
+        eprintln!("recording stopped: {}", err);
+        app.recorder = None;
+    }
+}
+
+// the match is over: no more handoffs, just the final board and a rematch or quit.
+// returns once the rematch is set up
+fn finish_match(app: &mut App, side: Side) {
+    // without a terminal to ask there is nothing to wait for
+    if unsafe { isatty(STDIN_FILENO) } != 1 {
+        draw(app, side, &[]);
+        quit(app);
+    }
+    if app.raw_mode.is_none() {
+        app.raw_mode = RawMode::enable().ok();
+    }
+
+    let prompt = ["[r] rematch │ [q] quit".to_string()];
+    draw(app, side, &prompt);
+    loop {
+        if handle_resize(app) {
+            draw(app, side, &prompt);
+        }
+        for key in read_keys(Duration::from_secs(3600)).unwrap_or_default() {
+            match key {
+                Key::Char('r') => {
+                    rematch(app);
+                    return;
+                }
+                Key::Char('q') => quit(app),
+                _ => {}
             }
         }
-        
-        if GAME_STATE == STATE_SERVE && SERVING_PLAYER == 0 {
-            BALL_X = 1.0;
-            BALL_Y = PADDLE_LEFT_Y;
-            BALL_DX = rand::rng().random_range(1.0..1.8);
-            BALL_DY = rand::rng().random_range(-0.7..0.7);
-            GAME_STATE = STATE_RALLY;
-            
-            ensure_minimum_ball_speed();
+    }
+}
+
+// same config, players and skills on a fresh seed. only the first match is recorded
+fn rematch(app: &mut App) {
+    let mut game = app.config.new_game(rand::random(), app.game.court);
+    game.skills = app.game.skills;
+    app.controllers = build_controllers(&app.kinds, &game);
+    if app.box_score.is_some() {
+        app.box_score = Some(BoxScore::new(&game));
+    }
+    *app.game = game;
+    publish_score(&app.game);
+
+    let message = format!("rematch! seed: {}", app.game.seed);
+    draw(app, app.game.turn, &[message]);
+    pause(app, Duration::from_millis(1500));
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("reports always serialize")
+}
+
+// one frame of a bench run. a decided match makes way for the next one, seeded like simulate's
+fn bench_frame(app: &mut App) {
+    let inputs = decide(&mut app.controllers, &app.game);
+    step(&mut app.game, &inputs);
+    publish_score(&app.game);
+
+    let bench = app.bench.as_mut().expect("only called for a bench run");
+    bench.frames += 1;
+    if !app.game.game_over {
+        return;
+    }
+
+    bench.matches += 1;
+    let seed = match_seed(bench.seed, bench.matches as u64);
+    let mut game = app.config.new_game(seed, app.game.court);
+    game.skills = app.game.skills;
+    app.controllers = build_controllers(&app.kinds, &game);
+    *app.game = game;
+}
+
+// one animated frame, with the point, game over and resize handling around it
+fn animate_frame(app: &mut App, side: Side) {
+    handle_resize(app);
+    let inputs = decide(&mut app.controllers, &app.game);
+    let events = step(&mut app.game, &inputs);
+    publish_score(&app.game);
+    record(app, &events);
+    notify(&mut app.observers, &app.game, &events);
+    if let Some(box_score) = &mut app.box_score {
+        for event in &events {
+            box_score.observe(&app.game, event);
         }
-        
-        let mut point_ended = false;
-        let mut winner = 0;
-        let mut is_miss = false;
-        let mut consecutive_static_frames = 0;
-        let mut last_ball_x = BALL_X;
-        let mut last_ball_y = BALL_Y;
-        
-        while !point_ended {
-            update_paddles();
-            update_ball();
-            
-            if BALL_X > NET_POSITION as f64 && BALL_DX < 0.0 && 
-               last_ball_x <= NET_POSITION as f64 {
-                BALL_DX = -BALL_DX;
+    }
+
+    if events
+        .iter()
+        .any(|event| matches!(event, Event::PointScored { .. } | Event::Let(_)))
+    {
+        visualize_point_end(app, &events, side);
+    }
+
+    if matches!(app.game.phase, Phase::ServeInPlay | Phase::Rally) {
+        draw(app, side, &[]);
+        let delay = random_frame_delay(&mut app.game);
+        pause(app, delay);
+    }
+
+    if app.game.game_over {
+        finish_match(app, side);
+    }
+}
+
+// `side`'s turns, for good: waits for the turn, plays until the turn passes and hands it over.
+// the classic trampoline starts every turn here afresh, everything else loops
+fn play_turn(side: Side) -> ! {
+    let handoff = handoff();
+    loop {
+        handoff.wait(side);
+        {
+            // held for the whole turn and let go before the handoff, which on the signal
+            // transports runs the other side's turn on this very thread
+            let mut app = app();
+            let app = &mut *app;
+            if let Some(bench) = &mut app.bench {
+                bench.arrived(app.format);
             }
-            
-            if (BALL_X - last_ball_x).abs() < 0.01 && (BALL_Y - last_ball_y).abs() < 0.01 {
-                consecutive_static_frames += 1;
-                if consecutive_static_frames > 5 {
-                    ensure_minimum_ball_speed();
-                    BALL_DX *= 1.5;
-                    consecutive_static_frames = 0;
+
+            loop {
+                match &app.bench {
+                    // nothing to play, the turn goes straight back
+                    Some(bench) if bench.bare => app.game.turn = side.opponent(),
+                    Some(_) => bench_frame(app),
+                    None => animate_frame(app, side),
                 }
-            } else {
-                consecutive_static_frames = 0;
-            }
-            
-            last_ball_x = BALL_X;
-            last_ball_y = BALL_Y;
-            
-            if BALL_X <= 1.0 && BALL_Y >= (PADDLE_LEFT_Y - PADDLE_SIZE) && BALL_Y <= (PADDLE_LEFT_Y + PADDLE_SIZE) {
-                if handle_potential_miss(true) {
-                    winner = 1;
-                    point_ended = true;
-                    is_miss = true;
-                } else {
-                    handle_paddle_hit(true);
+                if app.game.turn != side {
+                    break;
                 }
             }
-            
-            if BALL_X < 0.0 && !point_ended {
-                winner = 1;
-                point_ended = true;
-            }
-            
-            if BALL_X >= WIDTH as f64 && !point_ended {
-                winner = 0;
-                point_ended = true;
-            }
-            
-            if !point_ended {
-                draw_board("PING");
-            }
-            
-            if point_ended {
-                break;
-            }
-            
-            sleep(random_frame_delay());
-            
-            if BALL_X >= NET_POSITION as f64 && BALL_DX > 0.0 {
-                break;
+
+            if let Some(bench) = &mut app.bench {
+                bench.sent = Some(Instant::now());
             }
         }
-        
-        if point_ended {
-            visualize_point_end(winner, is_miss, "PING");
+        handoff.give(side.opponent());
+    }
+}
+
+fn flip() {
+    play_turn(Side::Ping);
+}
+
+fn flop() {
+    play_turn(Side::Pong);
+}
+
+fn main() {
+    let options = cli::parse(std::env::args().skip(1));
+
+    if options.print_config {
+        print!("{}", Config::defaults_toml());
+        return;
+    }
+
+    let mut config = match &options.config {
+        Some(path) => Config::load(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }),
+        None => Config::default(),
+    };
+    if let Some(points) = options.max_score {
+        config.rules.points_to_win = points;
+    }
+    if let Some(best_of) = options.best_of {
+        config.rules.best_of = best_of;
+    }
+    if let Some(delay) = options.frame_delay {
+        config.timing.frame_delay = delay;
+        config.timing.frame_variation = config.timing.frame_variation.min(delay);
+    }
+
+    match options.command {
+        Command::Replay => replay(&options, &config),
+        Command::Simulate => simulate(&options, &config),
+        Command::Bench if options.all_transports => compare(&options),
+        Command::Stress => stress(&options),
+        Command::Play | Command::Bench => start(options, config),
+    }
+}
+
+fn replay(options: &Options, config: &Config) {
+    let path = options
+        .file
+        .as_deref()
+        .expect("the parser insists on a file");
+    let replay = Replay::load(path).unwrap_or_else(|err| {
+        eprintln!("cannot load replay {}: {}", path, err);
+        std::process::exit(1);
+    });
+    for (name, recorded, current) in replay.mismatched_constants(config) {
+        eprintln!(
+            "warning: replay recorded with {} = {}, the current config uses {}",
+            name, recorded, current
+        );
+    }
+    if let Err(err) = viewer::play(&replay) {
+        eprintln!("replay viewer: {}", err);
+        std::process::exit(1);
+    }
+}
+
+// the sinks asked for on the command line
+fn open_observers(options: &Options) -> Vec<Box<dyn Observer>> {
+    let mut observers: Vec<Box<dyn Observer>> = Vec::new();
+    if let Some(path) = &options.events {
+        let log = EventLog::create(path).unwrap_or_else(|err| {
+            eprintln!("cannot write events to {}: {}", path, err);
+            std::process::exit(1);
+        });
+        observers.push(Box::new(log));
+    }
+    observers
+}
+
+fn finish_observers(observers: &mut [Box<dyn Observer>]) {
+    for observer in observers {
+        if let Err(err) = observer.finish() {
+            eprintln!("event log: {}", err);
         }
-        
-        kill(0, SIGUSR1);
     }
-    
-    loop {
-        sleep(Duration::from_millis(BASE_FRAME_DELAY));
+}
+
+fn simulate(options: &Options, config: &Config) {
+    let mut observers = open_observers(options);
+    let stats = run_batch(
+        options.matches.unwrap_or(DEFAULT_SIMULATED_MATCHES),
+        options.seed.unwrap_or_else(rand::random),
+        config,
+        &options.controllers,
+        options.skills.map(|skill| config.skill(skill)),
+        options.court.unwrap_or_default(),
+        &mut observers,
+    );
+    finish_observers(&mut observers);
+    match options.format {
+        Format::Text => println!("{}", stats),
+        Format::Json => println!("{}", to_json(&stats)),
     }
 }
 
-fn flop() {
-    unsafe {
-        if GAME_OVER {
-            draw_board("PONG");
-            sleep(Duration::from_millis(1000));
-            kill(0, SIGUSR1);
-            loop {
-                sleep(Duration::from_millis(BASE_FRAME_DELAY));
+// bench --transport all: every transport benched in a process of its own, since the signal
+// trampoline takes over the process it runs in, then the reports side by side
+fn compare(options: &Options) {
+    let program = std::env::current_exe().unwrap_or_else(|err| {
+        eprintln!("cannot find the program to run the benches: {}", err);
+        std::process::exit(1);
+    });
+    // every transport plays the same matches
+    let seed = options.seed.unwrap_or_else(rand::random).to_string();
+    let args = cli::strip_flags(
+        std::env::args().skip(1),
+        &["--transport", "--format", "--seed"],
+    );
+
+    let mut reports: Vec<BenchReport> = Vec::new();
+    for transport in Transport::ALL
+        .into_iter()
+        .filter(|transport| transport.supported())
+    {
+        let output = std::process::Command::new(&program)
+            .args(&args)
+            .args(["--transport", transport.name(), "--format", "json"])
+            .args(["--seed", &seed])
+            .stderr(Stdio::inherit())
+            .output();
+        let report = match output {
+            Ok(output) if output.status.success() => serde_json::from_slice(&output.stdout)
+                .map_err(|err| format!("unreadable report: {}", err)),
+            Ok(output) => Err(output.status.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        match report {
+            Ok(report) => reports.push(report),
+            Err(err) => {
+                eprintln!("bench over {} failed: {}", transport.name(), err);
+                std::process::exit(1);
             }
         }
-        
-        if GAME_STATE == STATE_SERVE && SERVING_PLAYER == 1 {
-            BALL_X = (WIDTH - 2) as f64;
-            BALL_Y = PADDLE_RIGHT_Y;
-            BALL_DX = -rand::rng().random_range(1.0..1.8);
-            BALL_DY = rand::rng().random_range(-0.7..0.7);
-            GAME_STATE = STATE_RALLY;
-            
-            ensure_minimum_ball_speed();
-        }
-        
-        let mut point_ended = false;
-        let mut winner = 0;
-        let mut is_miss = false;
-        let mut consecutive_static_frames = 0;
-        let mut last_ball_x = BALL_X;
-        let mut last_ball_y = BALL_Y;
-        
-        while !point_ended {
-            update_paddles();
-            update_ball();
-            
-            if BALL_X < NET_POSITION as f64 && BALL_DX > 0.0 && 
-               last_ball_x >= NET_POSITION as f64 {
-                BALL_DX = -BALL_DX;
-            }
-            
-            if (BALL_X - last_ball_x).abs() < 0.01 && (BALL_Y - last_ball_y).abs() < 0.01 {
-                consecutive_static_frames += 1;
-                if consecutive_static_frames > 5 {
-                    ensure_minimum_ball_speed();
-                    BALL_DX *= 1.5;
-                    consecutive_static_frames = 0;
-                }
-            } else {
-                consecutive_static_frames = 0;
-            }
-            
-            last_ball_x = BALL_X;
-            last_ball_y = BALL_Y;
-            
-            if BALL_X >= (WIDTH - 2) as f64 && 
-               BALL_Y >= (PADDLE_RIGHT_Y - PADDLE_SIZE) && 
-               BALL_Y <= (PADDLE_RIGHT_Y + PADDLE_SIZE) {
-                if handle_potential_miss(false) {
-                    winner = 0;
-                    point_ended = true;
-                    is_miss = true;
-                } else {
-                    handle_paddle_hit(false);
-                }
-            }
-            
-            if BALL_X < 0.0 && !point_ended {
-                winner = 1;
-                point_ended = true;
-            }
-            
-            if BALL_X >= WIDTH as f64 && !point_ended {
-                winner = 0;
-                point_ended = true;
-            }
-            
-            if !point_ended {
-                draw_board("PONG");
-            }
-            
-            if point_ended {
-                break;
-            }
-            
-            sleep(random_frame_delay());
-            
-            if BALL_X <= NET_POSITION as f64 && BALL_DX < 0.0 {
-                break;
+    }
+
+    match options.format {
+        Format::Text => println!("{}", Comparison(&reports)),
+        Format::Json => println!("{}", to_json(&reports)),
+    }
+}
+
+// runs the checks in stress.rs over one transport, every threaded one or the process-wide way of
+// flip and flop. exits 1 if any run failed
+fn stress(options: &Options) -> ! {
+    let transports: Vec<Option<Transport>> = if options.process_wide {
+        vec![None]
+    } else if options.all_transports {
+        Transport::ALL
+            .into_iter()
+            .filter(|transport| transport.threaded())
+            .map(Some)
+            .collect()
+    } else {
+        vec![Some(options.transport.unwrap_or(Transport::Tgkill))]
+    };
+
+    let handoffs = options.handoffs.unwrap_or(DEFAULT_HANDOFFS);
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let mut reports = Vec::new();
+    for transport in transports {
+        let report = stress::stress(transport, handoffs, seed).unwrap_or_else(|err| {
+            eprintln!("cannot run the stress test: {}", err);
+            std::process::exit(1);
+        });
+        if options.format == Format::Text {
+            if !reports.is_empty() {
+                println!();
             }
+            println!("{}", report);
         }
-        
-        if point_ended {
-            visualize_point_end(winner, is_miss, "PONG");
-        }
-        
-        kill(0, SIGUSR1);
+        reports.push(report);
     }
-    
-    loop {
-        sleep(Duration::from_millis(BASE_FRAME_DELAY));
+
+    if options.format == Format::Json {
+        match reports.as_slice() {
+            [report] => println!("{}", to_json(report)),
+            reports => println!("{}", to_json(&reports)),
+        }
     }
+    // a process-wide run can leave a player waiting for good, so this does not wait for it
+    let passed = reports.iter().all(|report| report.passed());
+    std::process::exit(if passed { 0 } else { 1 });
 }
 
-fn main() {
-    unsafe {
-        SCORE_PING = 0;
-        SCORE_PONG = 0;
-        RALLY_LENGTH = 0;
-        LONGEST_RALLY = 0;
-        GAME_STATE = STATE_SERVE;
-        SERVING_PLAYER = 0;
-        GAME_OVER = false;
-        
-        PADDLE_LEFT_Y = PADDLE_CENTER;
-        PADDLE_RIGHT_Y = PADDLE_CENTER;
-        
-        reset_for_serve(0);
-    }
-    
-    let mut act: sigaction = unsafe { std::mem::zeroed() };
-    act.sa_sigaction = handle_signal as usize;
-    act.sa_flags = SA_SIGINFO;
-    unsafe {
-        sigemptyset(&mut act.sa_mask);
-        sigaction(SIGUSR1, &act, std::ptr::null_mut());
+// play and bench: sets up the app and hands the first turn over, never returns
+fn start(options: Options, config: Config) -> ! {
+    let bench = options.command == Command::Bench;
+    let transport = options.transport.unwrap_or_default();
+
+    // without --court the board fills the terminal, or keeps the classic size on anything else
+    let court = options.court.unwrap_or_else(|| match terminal_size() {
+        Some((columns, rows)) if !bench => Court::fit(columns, rows).unwrap_or_else(|err| {
+            eprintln!("{}, make it bigger or pick a size with --court", err);
+            std::process::exit(1);
+        }),
+        _ => Court::default(),
+    });
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let mut game = config.new_game(seed, court);
+    game.skills = options.skills.map(|skill| config.skill(skill));
+
+    let recorder = options.record.as_ref().map(|path| {
+        Recorder::create(path, &game).unwrap_or_else(|err| {
+            eprintln!("cannot record to {}: {}", path, err);
+            std::process::exit(1);
+        })
+    });
+
+    let kinds = options.controllers.clone();
+    let controllers = build_controllers(&kinds, &game);
+    let box_score = (!transport.forks()).then(|| BoxScore::new(&game));
+    let game = shared(game);
+    let human = options.controllers.contains(&ControllerKind::Human);
+    // ctrl-c and kill must not leave the shell without echo or stuck on the alternate screen
+    term::on_quit_signals(handle_quit);
+    let raw_mode = human.then(|| {
+        RawMode::enable().unwrap_or_else(|err| {
+            eprintln!("human players need a terminal: {}", err);
+            std::process::exit(1);
+        })
+    });
+    // piped output keeps the plain escape stream
+    let alternate_screen = if !bench && unsafe { isatty(STDOUT_FILENO) } == 1 {
+        AlternateScreen::enter().ok()
+    } else {
+        None
+    };
+
+    // a panic in flip or flop must not leave the terminal on the alternate screen, nor the
+    // other player's thread waiting for a turn that never comes
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        term::restore();
+        default_hook(info);
+        std::process::exit(101);
+    }));
+
+    if SCORE
+        .set(shared([AtomicU32::new(0), AtomicU32::new(0)]))
+        .is_err()
+    {
+        unreachable!("the app is only started once");
+    }
+    let state = App {
+        game,
+        config,
+        recorder,
+        observers: open_observers(&options),
+        box_score,
+        stats_files: [options.stats_json.clone(), options.stats_csv.clone()],
+        kinds,
+        controllers,
+        raw_mode,
+        _alternate_screen: alternate_screen,
+        screen: Screen::new(),
+        render_stats: options.render_stats,
+        // a replay has a single court size, so a recorded match keeps its court
+        fit_court: options.court.is_none() && options.record.is_none(),
+        columns: terminal_size().map_or(usize::MAX, |(columns, _)| columns),
+        format: options.format,
+        bench: bench.then(|| {
+            let handoffs = options.handoffs.unwrap_or(DEFAULT_HANDOFFS);
+            let latencies = Latencies::with_capacity(handoffs).unwrap_or_else(|err| {
+                eprintln!("cannot map memory for {} handoffs: {}", handoffs, err);
+                std::process::exit(1);
+            });
+            shared(Bench {
+                transport,
+                seed,
+                handoffs,
+                bare: options.bare,
+                made: 0,
+                matches: 0,
+                frames: 0,
+                sent: None,
+                latencies,
+                started: Instant::now(),
+            })
+        }),
+    };
+    if APP.set(Mutex::new(state)).is_err() {
+        unreachable!("the app is only started once");
+    }
+
+    // flip goes first, as it always has
+    let handoff = transport
+        .create(Side::Ping, [flip, flop])
+        .unwrap_or_else(|err| {
+            term::restore();
+            eprintln!("cannot set up the {} handoff: {}", transport.name(), err);
+            std::process::exit(1);
+        });
+    if HANDOFF.set(handoff).is_err() {
+        unreachable!("the app is only started once");
+    }
+
+    if bench {
+        serve(transport);
     }
-    
+    term::watch_resize();
+
     print!("\x1B[2J\x1B[H");
     println!("alessandrods optimized nerd snippet");
+    println!("seed: {} (replay with --seed {})", seed, seed);
+    if let Some(path) = &options.record {
+        println!("recording to {}", path);
+    }
+    for side in [Side::Ping, Side::Pong] {
+        if options.controllers[side.index()] == ControllerKind::Human {
+            let end = match app().game.end_of(side) {
+                End::Left => "left",
+                End::Right => "right",
+            };
+            println!("you are {}, starting on the {}", side, end);
+        }
+    }
 
-    
     sleep(Duration::from_millis(1000));
     println!("\nrdy...");
     sleep(Duration::from_millis(1000));
@@ -699,6 +910,107 @@ fn main() {
     sleep(Duration::from_millis(1000));
     println!("go!");
     sleep(Duration::from_millis(500));
-    
-    flip();
+
+    serve(transport);
+}
+
+// moves `value` into shared memory, or gives up
+fn shared<T>(value: T) -> Shared<T> {
+    Shared::new(value).unwrap_or_else(|err| {
+        term::restore();
+        eprintln!("cannot map shared memory: {}", err);
+        std::process::exit(1);
+    })
+}
+
+// sends ping off on its first turn. the signal transports play both sides on this thread, the
+// threaded transports give pong a thread of its own and the process transport forks ping and
+// pong and referees them from here
+fn serve(transport: Transport) -> ! {
+    if transport.forks() {
+        // each process keeps its own controllers, only stepping them on its own turns, so the
+        // players aim differently than in a one process match on the same seed
+        let players = [Side::Ping, Side::Pong].map(fork_player);
+        referee(players);
+    }
+    if transport.threaded() {
+        thread::Builder::new()
+            .name("pong".to_string())
+            .spawn(|| {
+                handoff().enter(Side::Pong);
+                flop();
+            })
+            .unwrap_or_else(|err| {
+                term::restore();
+                eprintln!("cannot start pong's thread: {}", err);
+                std::process::exit(1);
+            });
+        handoff().enter(Side::Ping);
+        flip();
+    }
+    // the signal transports jump into ping's first turn from here and never come back
+    handoff().give(Side::Ping);
+    unreachable!("nothing hands the turn back to the server");
+}
+
+// a child process that plays `side`'s turns until the match ends. returns its pid
+fn fork_player(side: Side) -> pid_t {
+    let referee = unsafe { getpid() };
+    match unsafe { fork() } {
+        -1 => {
+            term::restore();
+            eprintln!("cannot fork {}: {}", side, io::Error::last_os_error());
+            std::process::exit(1);
+        }
+        0 => {
+            unsafe {
+                // gone with the referee, however it ends
+                prctl(PR_SET_PDEATHSIG, SIGKILL);
+                if getppid() != referee {
+                    _exit(1);
+                }
+                // ctrl-c reaches the whole process group, the referee answers it for both
+                signal(SIGINT, SIG_IGN);
+            }
+            handoff().enter(side);
+            match side {
+                Side::Ping => flip(),
+                Side::Pong => flop(),
+            }
+            unreachable!("flip and flop never return");
+        }
+        pid => pid,
+    }
+}
+
+// the parent of the ping and pong processes. the first to exit decides the match, the other is
+// taken down with it and its status becomes ours, so the shell sees one program as before
+fn referee(players: [pid_t; 2]) -> ! {
+    let mut status = 0;
+    let done = loop {
+        let pid = unsafe { waitpid(-1, &mut status, 0) };
+        // a resize interrupts the wait
+        if pid > 0 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            break pid;
+        }
+    };
+    for player in players {
+        if player != done {
+            unsafe {
+                kill(player, SIGKILL);
+                waitpid(player, std::ptr::null_mut(), 0);
+            }
+        }
+    }
+
+    let code = if done > 0 && WIFEXITED(status) {
+        WEXITSTATUS(status)
+    } else if done > 0 && WIFSIGNALED(status) {
+        // it had no chance to put the terminal back
+        term::restore();
+        128 + WTERMSIG(status)
+    } else {
+        1
+    };
+    std::process::exit(code);
 }
@@ -0,0 +1,192 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::game::{Ball, Event, GameState, Side};
+
+// anything that follows a match event by event, like the --events log. Send, as whichever
+// player's thread has the turn reports the events
+pub trait Observer: Send {
+    // `game` is the state at the end of the frame the event happened in
+    fn observe(&mut self, game: &GameState, event: &Event);
+
+    // nothing more is coming, write out whatever is still held back
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// hands a frame's events to every observer, in order
+pub fn notify(observers: &mut [Box<dyn Observer>], game: &GameState, events: &[Event]) {
+    for observer in observers {
+        for event in events {
+            observer.observe(game, event);
+        }
+    }
+}
+
+// one line of the --events file
+#[derive(Clone, Debug, Serialize)]
+pub struct EventRecord {
+    pub seed: u64,
+    pub frame: u64,
+    pub game: u32,
+    pub event: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub side: Option<Side>,
+    // how a point was decided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    pub score: [u32; 2],
+    pub games: [u32; 2],
+    pub ball: Ball,
+}
+
+impl EventRecord {
+    pub fn new(game: &GameState, event: &Event) -> EventRecord {
+        let reason = match event {
+            Event::PointScored { reason, .. } => Some(reason.name()),
+            _ => None,
+        };
+        EventRecord {
+            seed: game.seed,
+            frame: game.frame,
+            game: game.game_number,
+            event: event.name(),
+            side: event.side(),
+            reason,
+            score: game.score,
+            games: game.games,
+            ball: game.ball,
+        }
+    }
+}
+
+// writes every event as a json object on its own line
+pub struct EventLog<W: Write> {
+    out: W,
+    // the first write that failed. the log stops there and finish reports it
+    error: Option<io::Error>,
+}
+
+impl EventLog<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(EventLog::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> EventLog<W> {
+    pub fn new(out: W) -> Self {
+        EventLog { out, error: None }
+    }
+
+    fn write(&mut self, game: &GameState, event: &Event) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, &EventRecord::new(game, event))?;
+        self.out.write_all(b"\n")?;
+        // flushed per point, so a match cut short still leaves every finished point behind
+        if matches!(event, Event::PointScored { .. } | Event::GameOver { .. }) {
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> Observer for EventLog<W> {
+    fn observe(&mut self, game: &GameState, event: &Event) {
+        if self.error.is_none()
+            && let Err(err) = self.write(game, event)
+        {
+            self.error = Some(err);
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => self.out.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Inputs, step};
+
+    // a writer whose every write fails, numbered so the test can tell which one it kept
+    struct Broken {
+        writes: u32,
+    }
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            Err(io::Error::other(format!("write {} failed", self.writes)))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // every event of a short match, logged into memory
+    fn logged_match() -> (Vec<Event>, Vec<serde_json::Value>) {
+        let mut game = GameState::with_seed(4);
+        game.rules.points_to_win = 3;
+        let mut log = EventLog::new(Vec::new());
+        let mut seen = Vec::new();
+        while !game.game_over {
+            let events = step(&mut game, &Inputs::default());
+            for event in &events {
+                log.observe(&game, event);
+            }
+            seen.extend(events);
+        }
+        log.finish().expect("a Vec takes every write");
+        let text = String::from_utf8(log.out).expect("the log is utf-8");
+        let lines = text
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line is one json value"))
+            .collect();
+        (seen, lines)
+    }
+
+    #[test]
+    fn every_event_is_one_json_object_on_its_own_line() {
+        let (events, lines) = logged_match();
+        assert_eq!(lines.len(), events.len());
+        for (event, line) in events.iter().zip(&lines) {
+            assert!(line.is_object(), "{}", line);
+            assert_eq!(line["event"], event.name());
+            assert_eq!(line["seed"], 4);
+        }
+    }
+
+    #[test]
+    fn only_points_carry_a_reason() {
+        let (events, lines) = logged_match();
+        for (event, line) in events.iter().zip(&lines) {
+            match event {
+                Event::PointScored { reason, .. } => assert_eq!(line["reason"], reason.name()),
+                _ => assert!(line.get("reason").is_none(), "{}", line),
+            }
+        }
+        assert!(
+            events
+                .iter()
+                .any(|event| matches!(event, Event::PointScored { .. }))
+        );
+    }
+
+    #[test]
+    fn the_first_write_error_is_kept_for_finish() {
+        let game = GameState::with_seed(4);
+        let mut log = EventLog::new(Broken { writes: 0 });
+        log.observe(&game, &Event::Serve(Side::Ping));
+        log.observe(&game, &Event::WallBounce);
+        assert_eq!(log.out.writes, 1, "the log stops at the first failure");
+        let err = log.finish().expect_err("the failed write is reported");
+        assert_eq!(err.to_string(), "write 1 failed");
+    }
+}
@@ -0,0 +1,467 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::court::Court;
+use crate::game::{Ball, End, Event, GameState, Inputs, Side};
+use crate::{MAX_ALLOWED_SPEED, MIN_ALLOWED_SPEED, PADDLE_SIZE};
+
+// the club player's paddle speed, see skill.rs for the others
+pub const PADDLE_SPEED: f64 = 0.5;
+
+// how much of the spin bends dy each frame
+const SPIN_DRIFT: f64 = 0.02;
+// what a wall bounce leaves of dy and of the spin
+const WALL_DAMPING: f64 = 0.95;
+const WALL_SPIN_DAMPING: f64 = 0.7;
+// every paddle hit speeds the ball up by this factor, up to the maximum speed
+const SPEED_UP: f64 = 1.05;
+// dy and spin a ball picks up per paddle length it lands off centre
+const HIT_ANGLE: f64 = 0.8;
+const HIT_SPIN: f64 = 1.5;
+// chance of clipping the net for a ball passing over it
+const NET_CORD_CHANCE: f64 = 0.15;
+// of the balls that clip the net, how many fall back instead of rolling over
+const NET_CORD_DROP_CHANCE: f64 = 0.2;
+// what the cord leaves of dx for a ball it drops back and for one it rolls over
+const NET_CORD_DROP_SPEED: f64 = 0.8;
+const NET_CORD_ROLL_SPEED: f64 = 0.6;
+// a ball the cord slows below the minimum speed leaves at this many times it instead
+const NET_CORD_KICK: f64 = 1.2;
+// most the cord adds to or takes from dy
+const NET_CORD_WOBBLE: f64 = 0.2;
+// extra miss chance per row the ball lands beyond half a paddle from its centre
+const REACH_PENALTY: f64 = 0.15;
+// predictions give up after this long, a ball that slow is not worth chasing yet
+const MAX_PREDICTION_FRAMES: u32 = 500;
+
+// the ball's behaviour, loaded from the config file or left at the built-in values
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Physics {
+    pub min_speed: f64,
+    pub max_speed: f64,
+    // rows either side of a paddle's centre that still reach the ball
+    pub paddle_size: f64,
+    pub speed_up: f64,
+    pub hit_angle: f64,
+    pub hit_spin: f64,
+    pub spin_drift: f64,
+    pub wall_damping: f64,
+    pub wall_spin_damping: f64,
+    pub net_cord_chance: f64,
+    pub net_cord_drop_chance: f64,
+    pub net_cord_drop_speed: f64,
+    pub net_cord_roll_speed: f64,
+    pub net_cord_kick: f64,
+    pub net_cord_wobble: f64,
+    pub reach_penalty: f64,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Physics {
+            min_speed: MIN_ALLOWED_SPEED,
+            max_speed: MAX_ALLOWED_SPEED,
+            paddle_size: PADDLE_SIZE,
+            speed_up: SPEED_UP,
+            hit_angle: HIT_ANGLE,
+            hit_spin: HIT_SPIN,
+            spin_drift: SPIN_DRIFT,
+            wall_damping: WALL_DAMPING,
+            wall_spin_damping: WALL_SPIN_DAMPING,
+            net_cord_chance: NET_CORD_CHANCE,
+            net_cord_drop_chance: NET_CORD_DROP_CHANCE,
+            net_cord_drop_speed: NET_CORD_DROP_SPEED,
+            net_cord_roll_speed: NET_CORD_ROLL_SPEED,
+            net_cord_kick: NET_CORD_KICK,
+            net_cord_wobble: NET_CORD_WOBBLE,
+            reach_penalty: REACH_PENALTY,
+        }
+    }
+}
+
+pub fn ensure_minimum_ball_speed(game: &mut GameState) {
+    let rng = &mut game.rng;
+    raise_to_minimum_speed(
+        &mut game.ball,
+        &game.court,
+        game.physics.min_speed,
+        |spread| rng.random_range(-spread..spread),
+    );
+}
+
+// the body of ensure_minimum_ball_speed. `random_dy` is only asked for a value when the ball
+// has no usable direction of its own, which lets predictions run without touching the rng
+fn raise_to_minimum_speed(
+    ball: &mut Ball,
+    court: &Court,
+    min_speed: f64,
+    mut random_dy: impl FnMut(f64) -> f64,
+) {
+    let current_speed = ball.speed();
+
+    if current_speed < min_speed {
+        let direction = if ball.x < (court.width as f64 / 2.0) {
+            1.0
+        } else {
+            -1.0
+        };
+        let (norm_dx, norm_dy) = if current_speed > 0.0 {
+            (ball.dx / current_speed, ball.dy / current_speed)
+        } else {
+            (direction, random_dy(0.5))
+        };
+
+        ball.dx = norm_dx * min_speed;
+        ball.dy = norm_dy * min_speed;
+
+        if ball.dx.abs() < 0.1 {
+            ball.dx = direction * min_speed * 0.8;
+            if ball.dy == 0.0 {
+                ball.dy = random_dy(0.3);
+            }
+        }
+    }
+}
+
+pub fn update_paddles(game: &mut GameState, inputs: &Inputs) {
+    let (lowest, highest) = game.court.paddle_range(game.physics.paddle_size);
+    for side in [Side::Ping, Side::Pong] {
+        let speed = game.skills[side.index()].paddle_speed;
+        let paddle_y = &mut game.paddle_y[side.index()];
+        *paddle_y += inputs.delta(side).clamp(-speed, speed);
+        *paddle_y = paddle_y.clamp(lowest, highest);
+    }
+}
+
+// the straight segment a ball travels in one frame, before walls or the net get involved
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sweep {
+    pub x: f64,
+    pub y: f64,
+    pub dx: f64,
+    pub dy: f64,
+}
+
+impl Sweep {
+    // row where the segment crosses column `plane` while moving towards `end`.
+    // the row is held inside the walls the same way update_ball holds the ball
+    pub fn crossing(&self, court: &Court, plane: f64, end: End) -> Option<f64> {
+        let end_x = self.x + self.dx;
+        let crosses = match end {
+            End::Left => self.x > plane && end_x <= plane,
+            End::Right => self.x < plane && end_x >= plane,
+        };
+        if !crosses {
+            return None;
+        }
+
+        let t = (plane - self.x) / self.dx;
+        Some((self.y + self.dy * t).clamp(court.top_wall(), court.bottom_wall()))
+    }
+}
+
+// reflects a ball that went through the top or bottom wall back onto the board
+fn bounce_off_walls(ball: &mut Ball, court: &Court, physics: &Physics) -> bool {
+    let wall = if ball.y < court.top_wall() {
+        court.top_wall()
+    } else if ball.y > court.bottom_wall() {
+        court.bottom_wall()
+    } else {
+        return false;
+    };
+
+    ball.y = wall;
+    ball.dy = -ball.dy * physics.wall_damping;
+    ball.spin *= physics.wall_spin_damping;
+    true
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Landing {
+    // row where the ball crosses the paddle column
+    pub y: f64,
+    // frames from now until it gets there
+    pub frames: u32,
+}
+
+// where the game's ball will cross `end`'s paddle column if nobody touches it. follows
+// update_ball's spin drift, wall reflections and speed floor, but not the net cord, which is
+// down to chance
+pub fn predict_landing(game: &GameState, end: End) -> Option<Landing> {
+    let ball = &game.ball;
+    let court = &game.court;
+    let physics = &game.physics;
+    let heading_there = match end {
+        End::Left => ball.dx < 0.0,
+        End::Right => ball.dx > 0.0,
+    };
+    if !heading_there {
+        return None;
+    }
+
+    let plane = court.paddle_plane(end);
+    let mut ball = *ball;
+
+    for frames in 1..=MAX_PREDICTION_FRAMES {
+        ball.dy += ball.spin * physics.spin_drift;
+        raise_to_minimum_speed(&mut ball, court, physics.min_speed, |_| 0.0);
+
+        let sweep = Sweep {
+            x: ball.x,
+            y: ball.y,
+            dx: ball.dx,
+            dy: ball.dy,
+        };
+        if let Some(y) = sweep.crossing(court, plane, end) {
+            return Some(Landing { y, frames });
+        }
+
+        ball.x += ball.dx;
+        ball.y += ball.dy;
+        if bounce_off_walls(&mut ball, court, physics) {
+            raise_to_minimum_speed(&mut ball, court, physics.min_speed, |_| 0.0);
+        }
+    }
+
+    None
+}
+
+pub fn update_ball(game: &mut GameState, events: &mut Vec<Event>) -> Sweep {
+    let physics = game.physics;
+    game.ball.dy += game.ball.spin * physics.spin_drift;
+
+    ensure_minimum_ball_speed(game);
+
+    let sweep = Sweep {
+        x: game.ball.x,
+        y: game.ball.y,
+        dx: game.ball.dx,
+        dy: game.ball.dy,
+    };
+
+    game.ball.x += game.ball.dx;
+    game.ball.y += game.ball.dy;
+
+    let net = game.court.net();
+    let started_on_left_side = game.ball.x - game.ball.dx < net as f64;
+
+    if bounce_off_walls(&mut game.ball, &game.court, &physics) {
+        events.push(Event::WallBounce);
+
+        ensure_minimum_ball_speed(game);
+    }
+
+    let column = game.ball.x.round() as usize;
+    let very_close_to_net = (net - 1..=net + 1).contains(&column);
+
+    if very_close_to_net
+        && game.ball.y > game.court.top_wall()
+        && game.ball.y < game.court.bottom_wall()
+        && game.consecutive_net_hits < 1
+        && game.rng.random_bool(physics.net_cord_chance)
+    {
+        game.consecutive_net_hits += 1;
+        events.push(Event::NetCord);
+
+        // the cord either rolls the ball on over the net, or drops it back on the side it
+        // came from, where step() gives the point against whoever sent it
+        let ahead = sweep.dx.signum();
+        let ball = &mut game.ball;
+        if game.rng.random_bool(physics.net_cord_drop_chance) {
+            ball.dx = -ball.dx * physics.net_cord_drop_speed;
+            ball.x = net as f64 - 2.0 * ahead;
+        } else {
+            ball.dx *= physics.net_cord_roll_speed;
+            ball.x = net as f64 + 2.0 * ahead;
+        }
+
+        if ball.dx.abs() < physics.min_speed {
+            ball.dx = physics.min_speed * physics.net_cord_kick * ball.dx.signum();
+        }
+
+        if physics.net_cord_wobble > 0.0 {
+            ball.dy += game
+                .rng
+                .random_range(-physics.net_cord_wobble..physics.net_cord_wobble);
+        }
+
+        ensure_minimum_ball_speed(game);
+    } else if !very_close_to_net {
+        game.consecutive_net_hits = 0;
+    }
+
+    let ball = &mut game.ball;
+    let crossed_backwards = if started_on_left_side {
+        ball.x > net as f64 && ball.dx < 0.0
+    } else {
+        ball.x < net as f64 && ball.dx > 0.0
+    };
+    if crossed_backwards {
+        ball.dx = -ball.dx;
+    }
+
+    sweep
+}
+
+pub fn handle_paddle_hit(game: &mut GameState, side: Side) {
+    let end = game.end_of(side);
+    let paddle_y = game.paddle_y[side.index()];
+    let width = game.court.width;
+    let physics = game.physics;
+    let ball = &mut game.ball;
+
+    let hit_pos = (ball.y - paddle_y) / physics.paddle_size;
+
+    ball.dx = -ball.dx;
+
+    let new_speed = (ball.speed() * physics.speed_up).min(physics.max_speed);
+
+    ball.dy += hit_pos * physics.hit_angle;
+
+    ball.spin = hit_pos * physics.hit_spin;
+
+    let magnitude = ball.speed();
+    if magnitude > 0.0 {
+        ball.dx = (ball.dx / magnitude) * new_speed;
+        ball.dy = (ball.dy / magnitude) * new_speed;
+    } else {
+        ball.dx = match end {
+            End::Left => new_speed,
+            End::Right => -new_speed,
+        };
+        ball.dy = game.rng.random_range(-0.3..0.3);
+    }
+
+    ball.dy += game.rng.random_range(-0.1..0.1);
+
+    ball.x = match end {
+        End::Left => 3.0,
+        End::Right => (width - 4) as f64,
+    };
+
+    ensure_minimum_ball_speed(game);
+
+    game.rally_length += 1;
+}
+
+pub fn calculate_miss_probability(game: &GameState, side: Side) -> f64 {
+    let skill = game.skills[side.index()];
+    let speed = game.ball.speed();
+    let distance_from_paddle = (game.ball.y - game.paddle_y[side.index()]).abs();
+
+    let mut miss_prob = skill.miss_base;
+
+    miss_prob += (speed - 1.0) * skill.difficulty_scaling;
+
+    let sweet_spot = game.physics.paddle_size * 0.5;
+    if distance_from_paddle > sweet_spot {
+        miss_prob += (distance_from_paddle - sweet_spot) * game.physics.reach_penalty;
+    }
+
+    // no floor, an unbeatable player really can return everything it reaches
+    miss_prob.clamp(0.0, 0.95)
+}
+
+pub fn handle_potential_miss(game: &mut GameState, side: Side) -> bool {
+    let end = game.end_of(side);
+    let paddle_y = game.paddle_y[side.index()];
+
+    let miss_prob = calculate_miss_probability(game, side);
+
+    if game.rng.random_bool(miss_prob) {
+        let court = game.court;
+        let ball = &mut game.ball;
+        ball.x = match end {
+            End::Left => -1.0,
+            End::Right => court.width as f64 + 1.0,
+        };
+
+        let miss_offset = game.rng.random_range(1.5..2.5);
+        if ball.y < paddle_y {
+            ball.y = (paddle_y - miss_offset).max(court.top_wall());
+        } else {
+            ball.y = (paddle_y + miss_offset).min(court.bottom_wall());
+        }
+
+        return true;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sweep(x: f64, y: f64, dx: f64, dy: f64) -> Sweep {
+        Sweep { x, y, dx, dy }
+    }
+
+    #[test]
+    fn a_sweep_finds_the_row_where_it_crosses_the_plane() {
+        let court = Court::default();
+        let plane = court.paddle_plane(End::Left);
+        // from column 5 to -3, crossing column 1 half way
+        let crossing = sweep(5.0, 8.0, -8.0, 2.0).crossing(&court, plane, End::Left);
+        assert_eq!(crossing, Some(9.0));
+    }
+
+    #[test]
+    fn a_sweep_that_stops_short_or_moves_away_does_not_cross() {
+        let court = Court::default();
+        let plane = court.paddle_plane(End::Right);
+        assert_eq!(
+            sweep(60.0, 8.0, 5.0, 0.0).crossing(&court, plane, End::Right),
+            None
+        );
+        assert_eq!(
+            sweep(73.0, 8.0, -6.0, 0.0).crossing(&court, plane, End::Right),
+            None
+        );
+        assert_eq!(
+            sweep(73.0, 8.0, 6.0, 0.0).crossing(&court, plane, End::Left),
+            None
+        );
+    }
+
+    #[test]
+    fn a_sweep_crossing_beyond_a_wall_is_held_on_the_wall() {
+        let court = Court::default();
+        let plane = court.paddle_plane(End::Right);
+        let crossing = sweep(70.0, 15.0, 8.0, 10.0).crossing(&court, plane, End::Right);
+        assert_eq!(crossing, Some(court.bottom_wall()));
+    }
+
+    #[test]
+    fn a_hit_sends_the_ball_back_no_faster_than_max_speed() {
+        let mut game = GameState::with_seed(3);
+        game.ball = Ball {
+            x: 1.0,
+            y: game.paddle_y[0],
+            dx: -game.physics.max_speed,
+            dy: 0.0,
+            spin: 0.0,
+        };
+        handle_paddle_hit(&mut game, Side::Ping);
+        assert!(game.ball.dx > 0.0);
+        assert!(game.ball.speed() <= game.physics.max_speed + 0.2);
+        assert_eq!(game.rally_length, 1);
+    }
+
+    #[test]
+    fn predictions_follow_the_ball_off_a_wall() {
+        let mut game = GameState::with_seed(3);
+        game.ball = Ball {
+            x: 40.0,
+            y: 3.0,
+            dx: 6.0,
+            dy: -2.0,
+            spin: 0.0,
+        };
+        let landing = predict_landing(&game, End::Right).expect("the ball is heading right");
+        assert!(landing.y > game.court.top_wall());
+        assert_eq!(landing.frames, 6);
+        assert_eq!(predict_landing(&game, End::Left), None);
+    }
+}
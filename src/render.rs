@@ -0,0 +1,177 @@
+use std::fmt::{self, Write};
+
+use crate::game::{End, Event, GameState, Phase, PointReason, Side};
+
+pub fn draw_board(game: &GameState, current_player: Side) -> String {
+    let mut out = String::new();
+    write_board(&mut out, game, current_player).expect("writing to a String cannot fail");
+    out
+}
+
+// the one-line commentary shown under the board, if the event deserves one
+pub fn describe_event(event: &Event) -> Option<String> {
+    match *event {
+        Event::Serve(side) => Some(format!("{} serves", side)),
+        Event::Let(side) => Some(format!("let! {} serves again", side)),
+        Event::NetCord => Some("net cord!".to_string()),
+        Event::GameWon { winner } => Some(format!("game to {}!", winner)),
+        Event::EndsChanged => Some("players change ends".to_string()),
+        Event::PointScored { winner, reason } => Some(match reason {
+            PointReason::Miss(side) => format!("{} missed the ball. point to {}!", side, winner),
+            PointReason::Out(side) => {
+                format!("ball went out on {}s side. point to {}!", side, winner)
+            }
+            PointReason::Fault(side) => format!("service fault by {}. point to {}!", side, winner),
+        }),
+        _ => None,
+    }
+}
+
+// cuts `line` down to `width` characters, so a narrow court does not wrap its header
+fn push_clipped(out: &mut String, line: &str, width: usize) {
+    out.extend(line.chars().take(width));
+    out.push('\n');
+}
+
+fn write_board(out: &mut String, game: &GameState, current_player: Side) -> fmt::Result {
+    let court = game.court;
+    let border = "-".repeat(court.width + 2);
+    let mut header = String::new();
+    write_header(&mut header, game, current_player)?;
+    for line in header.lines() {
+        push_clipped(out, line, border.len());
+    }
+    writeln!(out, "{}", border)?;
+
+    let bx = game.ball.x.round() as i64;
+    let by = game.ball.y.round() as i64;
+    let left_paddle_y = game.paddle_y[game.player_at(End::Left).index()].round() as i64;
+    let right_paddle_y = game.paddle_y[game.player_at(End::Right).index()].round() as i64;
+    let (width, height, net) = (court.width as i64, court.height as i64, court.net() as i64);
+    let ball_in_bounds = (0..width).contains(&bx) && (0..height).contains(&by);
+
+    for y in 0..height {
+        out.push('║');
+        for x in 0..width {
+            if x == 0 && (left_paddle_y - 1..=left_paddle_y + 1).contains(&y) {
+                out.push('▌');
+            } else if x == width - 1 && (right_paddle_y - 1..=right_paddle_y + 1).contains(&y) {
+                out.push('▐');
+            } else if x == net {
+                out.push('│');
+            } else if ball_in_bounds && x == bx && y == by {
+                out.push('●');
+            } else {
+                out.push(' ');
+            }
+        }
+        out.push_str("║\n");
+    }
+
+    writeln!(out, "{}", border)?;
+
+    if let Some(winner) = game.winner() {
+        let [score_ping, score_pong] = game.score;
+        writeln!(out, "game is game. winner is {}", winner)?;
+        writeln!(
+            out,
+            "final score: ping {} - {} pong",
+            score_ping, score_pong
+        )?;
+        if game.rules.best_of > 1 {
+            writeln!(
+                out,
+                "games: ping {} - {} pong",
+                game.games[0], game.games[1]
+            )?;
+        }
+        writeln!(out, "longest rally: {} hits", game.longest_rally)?;
+    }
+
+    Ok(())
+}
+
+// the status lines above the court
+fn write_header(out: &mut String, game: &GameState, current_player: Side) -> fmt::Result {
+    let bx = game.ball.x.round() as i64;
+    let by = game.ball.y.round() as i64;
+    let [score_ping, score_pong] = game.score;
+    let current = current_player.name().to_uppercase();
+
+    writeln!(
+        out,
+        "---------------------- alessandrods nerd snippet ----------------------"
+    )?;
+    writeln!(
+        out,
+        "ping: {:<2}  pong: {:<2}  │  current: {:<4}  │ serving: {:<4} │ rally: {:<3}",
+        score_ping, score_pong, current, game.serving_player, game.rally_length
+    )?;
+    let game_state = if game.is_deuce() {
+        match score_ping.cmp(&score_pong) {
+            std::cmp::Ordering::Equal => "deuce".to_string(),
+            std::cmp::Ordering::Greater => "advantage ping".to_string(),
+            std::cmp::Ordering::Less => "advantage pong".to_string(),
+        }
+    } else {
+        String::new()
+    };
+    writeln!(
+        out,
+        "games: ping {} - {} pong  │  game {} of best of {}  │ left: {:<4} │ {}",
+        game.games[0],
+        game.games[1],
+        game.game_number,
+        game.rules.best_of,
+        game.player_at(End::Left),
+        game_state
+    )?;
+    writeln!(
+        out,
+        "║ ball speed: {:.2} │ longest rally: {:<3} │ {:<24}",
+        game.ball.speed(),
+        game.longest_rally,
+        if game.game_over {
+            "game over!"
+        } else {
+            match game.phase {
+                Phase::Serve => "serving...",
+                Phase::ServeInPlay => "serve in play",
+                Phase::Rally => "in progress",
+                Phase::PointEnd => "point ended",
+            }
+        }
+    )?;
+
+    let direction = if game.ball.dx > 0.0 {
+        "→"
+    } else if game.ball.dx < 0.0 {
+        "←"
+    } else {
+        "-"
+    };
+    let vert_dir = if game.ball.dy > 0.0 {
+        "↓"
+    } else if game.ball.dy < 0.0 {
+        "↑"
+    } else {
+        "-"
+    };
+    writeln!(
+        out,
+        "ball direction: {}{} │ position: ({},{}) │ ball in {:?} side ",
+        direction,
+        vert_dir,
+        bx,
+        by,
+        if bx < game.court.net() as i64 {
+            format!("{}s", game.player_at(End::Left))
+        } else {
+            format!("{}s", game.player_at(End::Right))
+        }
+    )?;
+    writeln!(
+        out,
+        "----------------------------------------------------------------------"
+    )
+}
@@ -0,0 +1,527 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::config::Config;
+use crate::court::Court;
+use crate::game::{Ball, Event, GameState, Phase, PointReason, Rules, Side};
+
+// bump whenever the frame line layout changes
+pub const REPLAY_VERSION: u32 = 1;
+const MAGIC: &str = "sig-ping-pong replay";
+
+// one line per frame after the header:
+// tick phase turn server score_ping score_pong games_ping games_pong left_player rally
+// x y dx dy spin ping_y pong_y game_number longest_rally [events..]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub tick: u64,
+    pub phase: Phase,
+    pub game_over: bool,
+    pub turn: Side,
+    pub serving_player: Side,
+    pub score: [u32; 2],
+    pub games: [u32; 2],
+    pub ends_swapped: bool,
+    pub rally_length: u32,
+    pub ball: Ball,
+    pub paddle_y: [f64; 2],
+    pub game_number: u32,
+    pub longest_rally: u32,
+    pub events: Vec<Event>,
+}
+
+impl Frame {
+    pub fn capture(tick: u64, game: &GameState, events: &[Event]) -> Frame {
+        Frame {
+            tick,
+            phase: game.phase,
+            game_over: game.game_over,
+            turn: game.turn,
+            serving_player: game.serving_player,
+            score: game.score,
+            games: game.games,
+            ends_swapped: game.ends_swapped,
+            rally_length: game.rally_length,
+            ball: game.ball,
+            paddle_y: game.paddle_y,
+            game_number: game.game_number,
+            longest_rally: game.longest_rally,
+            events: events.to_vec(),
+        }
+    }
+
+    // copies the recorded snapshot onto a game so it can be drawn
+    pub fn apply(&self, game: &mut GameState) {
+        game.phase = self.phase;
+        game.game_over = self.game_over;
+        game.turn = self.turn;
+        game.serving_player = self.serving_player;
+        game.score = self.score;
+        game.games = self.games;
+        game.ends_swapped = self.ends_swapped;
+        game.rally_length = self.rally_length;
+        game.ball = self.ball;
+        game.paddle_y = self.paddle_y;
+        game.game_number = self.game_number;
+        game.longest_rally = self.longest_rally;
+    }
+
+    fn write_line(&self, out: &mut impl Write) -> io::Result<()> {
+        let phase = if self.game_over {
+            "over"
+        } else {
+            match self.phase {
+                Phase::Serve => "serve",
+                Phase::ServeInPlay => "serve_in_play",
+                Phase::Rally => "rally",
+                Phase::PointEnd => "point_end",
+            }
+        };
+        write!(
+            out,
+            "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+            self.tick,
+            phase,
+            self.turn,
+            self.serving_player,
+            self.score[0],
+            self.score[1],
+            self.games[0],
+            self.games[1],
+            if self.ends_swapped {
+                Side::Pong
+            } else {
+                Side::Ping
+            },
+            self.rally_length,
+            self.ball.x,
+            self.ball.y,
+            self.ball.dx,
+            self.ball.dy,
+            self.ball.spin,
+            self.paddle_y[0],
+            self.paddle_y[1],
+            self.game_number,
+            self.longest_rally
+        )?;
+        for event in &self.events {
+            write!(out, " {}", event_tag(event))?;
+        }
+        writeln!(out)
+    }
+
+    fn parse_line(line: &str) -> Option<Frame> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 19 {
+            return None;
+        }
+
+        let (phase, game_over) = match fields[1] {
+            "serve" => (Phase::Serve, false),
+            "serve_in_play" => (Phase::ServeInPlay, false),
+            "rally" => (Phase::Rally, false),
+            "point_end" => (Phase::PointEnd, false),
+            "over" => (Phase::Serve, true),
+            _ => return None,
+        };
+        let float = |i: usize| fields[i].parse::<f64>().ok();
+
+        Some(Frame {
+            tick: fields[0].parse().ok()?,
+            phase,
+            game_over,
+            turn: Side::from_name(fields[2])?,
+            serving_player: Side::from_name(fields[3])?,
+            score: [fields[4].parse().ok()?, fields[5].parse().ok()?],
+            games: [fields[6].parse().ok()?, fields[7].parse().ok()?],
+            ends_swapped: Side::from_name(fields[8])? == Side::Pong,
+            rally_length: fields[9].parse().ok()?,
+            ball: Ball {
+                x: float(10)?,
+                y: float(11)?,
+                dx: float(12)?,
+                dy: float(13)?,
+                spin: float(14)?,
+            },
+            paddle_y: [float(15)?, float(16)?],
+            game_number: fields[17].parse().ok()?,
+            longest_rally: fields[18].parse().ok()?,
+            events: fields[19..]
+                .iter()
+                .map(|tag| parse_event_tag(tag))
+                .collect::<Option<_>>()?,
+        })
+    }
+}
+
+fn event_tag(event: &Event) -> String {
+    match *event {
+        Event::Serve(side) => format!("serve:{}", side),
+        Event::Let(side) => format!("let:{}", side),
+        Event::PaddleHit(side) => format!("hit:{}", side),
+        Event::WallBounce => "wall".to_string(),
+        Event::NetCord => "net".to_string(),
+        Event::Miss(side) => format!("miss:{}", side),
+        Event::Out(side) => format!("out:{}", side),
+        Event::PointScored { winner, reason } => {
+            format!("point:{}:{}:{}", winner, reason.name(), reason.side())
+        }
+        Event::ServeChange(side) => format!("server:{}", side),
+        Event::Handoff(side) => format!("handoff:{}", side),
+        Event::GameWon { winner } => format!("game:{}", winner),
+        Event::EndsChanged => "ends".to_string(),
+        Event::GameOver { winner } => format!("over:{}", winner),
+    }
+}
+
+fn parse_event_tag(tag: &str) -> Option<Event> {
+    let parts: Vec<&str> = tag.split(':').collect();
+    let side = |i: usize| parts.get(i).and_then(|name| Side::from_name(name));
+
+    Some(match parts[0] {
+        "serve" => Event::Serve(side(1)?),
+        "let" => Event::Let(side(1)?),
+        "hit" => Event::PaddleHit(side(1)?),
+        "wall" => Event::WallBounce,
+        "net" => Event::NetCord,
+        "miss" => Event::Miss(side(1)?),
+        "out" => Event::Out(side(1)?),
+        "point" => Event::PointScored {
+            winner: side(1)?,
+            reason: match *parts.get(2)? {
+                "miss" => PointReason::Miss(side(3)?),
+                "out" => PointReason::Out(side(3)?),
+                "fault" => PointReason::Fault(side(3)?),
+                _ => return None,
+            },
+        },
+        "server" => Event::ServeChange(side(1)?),
+        "handoff" => Event::Handoff(side(1)?),
+        "game" => Event::GameWon { winner: side(1)? },
+        "ends" => Event::EndsChanged,
+        "over" => Event::GameOver { winner: side(1)? },
+        _ => return None,
+    })
+}
+
+// the tuning `game` is played with, so a replay can be checked against the current config.
+// the names are those of the constants the values used to be
+pub fn constants(game: &GameState) -> Vec<(&'static str, String)> {
+    let physics = &game.physics;
+    vec![
+        ("BASE_FRAME_DELAY", game.timing.frame_delay.to_string()),
+        ("FRAME_VARIATION", game.timing.frame_variation.to_string()),
+        ("MAX_ALLOWED_SPEED", physics.max_speed.to_string()),
+        ("MIN_ALLOWED_SPEED", physics.min_speed.to_string()),
+        ("PADDLE_SIZE", physics.paddle_size.to_string()),
+        ("SPEED_UP", physics.speed_up.to_string()),
+        ("HIT_ANGLE", physics.hit_angle.to_string()),
+        ("HIT_SPIN", physics.hit_spin.to_string()),
+        ("SPIN_DRIFT", physics.spin_drift.to_string()),
+        ("WALL_DAMPING", physics.wall_damping.to_string()),
+        ("WALL_SPIN_DAMPING", physics.wall_spin_damping.to_string()),
+        ("NET_CORD_CHANCE", physics.net_cord_chance.to_string()),
+        (
+            "NET_CORD_DROP_CHANCE",
+            physics.net_cord_drop_chance.to_string(),
+        ),
+        (
+            "NET_CORD_DROP_SPEED",
+            physics.net_cord_drop_speed.to_string(),
+        ),
+        (
+            "NET_CORD_ROLL_SPEED",
+            physics.net_cord_roll_speed.to_string(),
+        ),
+        ("NET_CORD_KICK", physics.net_cord_kick.to_string()),
+        ("NET_CORD_WOBBLE", physics.net_cord_wobble.to_string()),
+        ("REACH_PENALTY", physics.reach_penalty.to_string()),
+        ("MAX_SCORE", game.rules.points_to_win.to_string()),
+    ]
+}
+
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub rules: Rules,
+    pub court: Court,
+    pub constants: Vec<(String, String)>,
+    pub frames: Vec<Frame>,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Replay> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read(input: impl BufRead) -> io::Result<Replay> {
+        let invalid = |line: usize, what: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", line, what),
+            )
+        };
+        let mut lines = input.lines().enumerate().map(|(n, line)| (n + 1, line));
+
+        let (_, header) = lines.next().ok_or_else(|| invalid(1, "empty replay"))?;
+        let version = header?
+            .strip_prefix(MAGIC)
+            .and_then(|rest| rest.trim().strip_prefix('v'))
+            .and_then(|v| v.parse::<u32>().ok())
+            .ok_or_else(|| invalid(1, "not a sig-ping-pong replay"))?;
+        if version != REPLAY_VERSION {
+            return Err(invalid(
+                1,
+                &format!(
+                    "replay version {} is not supported (expected {})",
+                    version, REPLAY_VERSION
+                ),
+            ));
+        }
+
+        let mut replay = Replay {
+            version,
+            seed: 0,
+            rules: Rules::default(),
+            court: Court::default(),
+            constants: Vec::new(),
+            frames: Vec::new(),
+        };
+
+        for (n, line) in lines {
+            let line = line?;
+            let mut words = line.split_whitespace();
+            match words.next() {
+                None => {}
+                Some("seed") => {
+                    replay.seed = words
+                        .next()
+                        .and_then(|seed| seed.parse().ok())
+                        .ok_or_else(|| invalid(n, "bad seed"))?;
+                }
+                Some("rules") => {
+                    let mut number = || words.next().and_then(|n| n.parse().ok());
+                    replay.rules = match (number(), number()) {
+                        (Some(points_to_win), Some(best_of)) => Rules {
+                            points_to_win,
+                            best_of,
+                        },
+                        _ => return Err(invalid(n, "bad rules")),
+                    };
+                }
+                Some("court") => {
+                    let mut number = || words.next().and_then(|n| n.parse().ok());
+                    replay.court = match (number(), number()) {
+                        (Some(width), Some(height)) => {
+                            Court::new(width, height).map_err(|err| invalid(n, &err))?
+                        }
+                        _ => return Err(invalid(n, "bad court")),
+                    };
+                }
+                Some("const") => match (words.next(), words.next()) {
+                    (Some(name), Some(value)) => {
+                        replay.constants.push((name.to_string(), value.to_string()))
+                    }
+                    _ => return Err(invalid(n, "bad constant")),
+                },
+                Some(_) => {
+                    let frame = Frame::parse_line(&line).ok_or_else(|| invalid(n, "bad frame"))?;
+                    replay.frames.push(frame);
+                }
+            }
+        }
+
+        Ok(replay)
+    }
+
+    // the settings the match was played with
+    pub fn config(&self) -> Config {
+        let mut config = Config {
+            rules: self.rules,
+            ..Config::default()
+        };
+        let timing = &mut config.timing;
+        let physics = &mut config.physics;
+        for (name, value) in &self.constants {
+            match name.as_str() {
+                "BASE_FRAME_DELAY" => set(&mut timing.frame_delay, value),
+                "FRAME_VARIATION" => set(&mut timing.frame_variation, value),
+                "MAX_ALLOWED_SPEED" => set(&mut physics.max_speed, value),
+                "MIN_ALLOWED_SPEED" => set(&mut physics.min_speed, value),
+                "PADDLE_SIZE" => set(&mut physics.paddle_size, value),
+                "SPEED_UP" => set(&mut physics.speed_up, value),
+                "HIT_ANGLE" => set(&mut physics.hit_angle, value),
+                "HIT_SPIN" => set(&mut physics.hit_spin, value),
+                "SPIN_DRIFT" => set(&mut physics.spin_drift, value),
+                "WALL_DAMPING" => set(&mut physics.wall_damping, value),
+                "WALL_SPIN_DAMPING" => set(&mut physics.wall_spin_damping, value),
+                "NET_CORD_CHANCE" => set(&mut physics.net_cord_chance, value),
+                "NET_CORD_DROP_CHANCE" => set(&mut physics.net_cord_drop_chance, value),
+                "NET_CORD_DROP_SPEED" => set(&mut physics.net_cord_drop_speed, value),
+                "NET_CORD_ROLL_SPEED" => set(&mut physics.net_cord_roll_speed, value),
+                "NET_CORD_KICK" => set(&mut physics.net_cord_kick, value),
+                "NET_CORD_WOBBLE" => set(&mut physics.net_cord_wobble, value),
+                "REACH_PENALTY" => set(&mut physics.reach_penalty, value),
+                _ => {}
+            }
+        }
+        config
+    }
+
+    // physics constants whose recorded value differs from `config`'s. rules and timing are
+    // not compared, the command line sets those per match, so the recording is the only record
+    // of them. both sides are written out by constants() from the same match, so equal values
+    // always compare equal
+    pub fn mismatched_constants(&self, config: &Config) -> Vec<(String, String, String)> {
+        let recorded = self.config().new_game(self.seed, self.court);
+        let mut current = recorded.clone();
+        current.physics = config.physics;
+        constants(&recorded)
+            .into_iter()
+            .zip(constants(&current))
+            .filter(|((_, recorded), (_, current))| recorded != current)
+            .map(|((name, recorded), (_, current))| (name.to_string(), recorded, current))
+            .collect()
+    }
+}
+
+fn set<T: std::str::FromStr>(field: &mut T, value: &str) {
+    if let Ok(value) = value.parse() {
+        *field = value;
+    }
+}
+
+// streams frames out as the match is played, flushing after every point
+pub struct Recorder<W: Write> {
+    out: W,
+    tick: u64,
+}
+
+impl Recorder<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>, game: &GameState) -> io::Result<Self> {
+        Recorder::new(BufWriter::new(File::create(path)?), game)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut out: W, game: &GameState) -> io::Result<Self> {
+        writeln!(out, "{} v{}", MAGIC, REPLAY_VERSION)?;
+        writeln!(out, "seed {}", game.seed)?;
+        writeln!(
+            out,
+            "rules {} {}",
+            game.rules.points_to_win, game.rules.best_of
+        )?;
+        writeln!(out, "court {} {}", game.court.width, game.court.height)?;
+        for (name, value) in constants(game) {
+            writeln!(out, "const {} {}", name, value)?;
+        }
+
+        let mut recorder = Recorder { out, tick: 0 };
+        recorder.record(game, &[])?;
+        Ok(recorder)
+    }
+
+    pub fn record(&mut self, game: &GameState, events: &[Event]) -> io::Result<()> {
+        Frame::capture(self.tick, game, events).write_line(&mut self.out)?;
+        self.tick += 1;
+
+        let point_over = events
+            .iter()
+            .any(|event| matches!(event, Event::PointScored { .. } | Event::GameOver { .. }));
+        if point_over {
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Inputs, step};
+
+    // a whole best of three to 3, recorded into memory, with the frames it should read back as
+    fn recorded_match() -> (GameState, Vec<u8>, Vec<Frame>) {
+        let mut game = GameState::with_seed(3);
+        game.rules = Rules {
+            points_to_win: 3,
+            best_of: 3,
+        };
+        let mut recorder = Recorder::new(Vec::new(), &game).expect("a Vec takes every write");
+        let mut frames = vec![Frame::capture(0, &game, &[])];
+        while !game.game_over {
+            let events = step(&mut game, &Inputs::default());
+            recorder
+                .record(&game, &events)
+                .expect("a Vec takes every write");
+            frames.push(Frame::capture(frames.len() as u64, &game, &events));
+        }
+        let bytes = recorder.finish().expect("a Vec takes every write");
+        (game, bytes, frames)
+    }
+
+    #[test]
+    fn a_recorded_match_reads_back_frame_for_frame() {
+        let (game, bytes, frames) = recorded_match();
+        let replay = Replay::read(bytes.as_slice()).expect("the recording reads back");
+        assert_eq!(replay.version, REPLAY_VERSION);
+        assert_eq!(replay.seed, 3);
+        assert_eq!(replay.rules, game.rules);
+        assert_eq!(replay.court, game.court);
+        assert_eq!(replay.frames, frames);
+        assert!(frames.last().expect("a match has frames").game_number > 1);
+        assert_eq!(replay.config().new_game(3, game.court).rules, game.rules);
+        assert_eq!(replay.config().physics, game.physics);
+        assert!(replay.mismatched_constants(&Config::default()).is_empty());
+    }
+
+    #[test]
+    fn an_applied_frame_restores_the_game_it_was_taken_from() {
+        let (game, _, frames) = recorded_match();
+        let mut board = GameState::with_court(game.seed, game.rules, game.court);
+        frames.last().expect("a match has frames").apply(&mut board);
+        assert_eq!(board.game_number, game.game_number);
+        assert_eq!(board.longest_rally, game.longest_rally);
+        assert_eq!(board.games, game.games);
+        assert!(board.game_over);
+    }
+
+    #[test]
+    fn any_other_version_is_refused() {
+        let (_, bytes, _) = recorded_match();
+        let text = String::from_utf8(bytes).expect("replays are utf-8");
+        let header = format!("{} v{}", MAGIC, REPLAY_VERSION);
+        for version in [0, REPLAY_VERSION + 1] {
+            let other = text.replacen(&header, &format!("{} v{}", MAGIC, version), 1);
+            let err = Replay::read(other.as_bytes()).expect_err("only one version reads");
+            assert!(err.to_string().contains("not supported"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn only_the_physics_is_compared_with_a_config() {
+        let (_, bytes, _) = recorded_match();
+        let replay = Replay::read(bytes.as_slice()).expect("the recording reads back");
+        let mut config = Config::default();
+        config.rules.points_to_win = 7;
+        config.timing.frame_delay += 10;
+        assert!(replay.mismatched_constants(&config).is_empty());
+
+        config.physics.max_speed += 0.5;
+        config.physics.net_cord_kick += 0.5;
+        let mismatched = replay.mismatched_constants(&config);
+        let names: Vec<&str> = mismatched
+            .iter()
+            .map(|(name, _, _)| name.as_str())
+            .collect();
+        assert_eq!(names, ["MAX_ALLOWED_SPEED", "NET_CORD_KICK"]);
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+use std::io::{self, Write};
+
+// unchanged cells shorter than this between two changes are rewritten rather than skipped,
+// a cursor move costs more than a few characters
+const MAX_SKIPPED_CELLS: usize = 6;
+
+const CLEAR: &str = "\x1B[2J\x1B[H";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub frames: u64,
+    pub full_redraws: u64,
+    // what was actually written
+    pub bytes: u64,
+    // what clearing and reprinting every frame would have cost
+    pub full_bytes: u64,
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let per_frame = self.bytes.checked_div(self.frames).unwrap_or(0);
+        let saved = if self.full_bytes == 0 {
+            0.0
+        } else {
+            100.0 - self.bytes as f64 * 100.0 / self.full_bytes as f64
+        };
+        write!(
+            f,
+            "render: {} frames, {} bytes ({} per frame), {} full redraws, {:.1}% saved",
+            self.frames, self.bytes, per_frame, self.full_redraws, saved
+        )
+    }
+}
+
+// double-buffered terminal output. keeps the last frame and only sends the cells that changed
+#[derive(Clone, Debug, Default)]
+pub struct Screen {
+    previous: Vec<Vec<char>>,
+    // the terminal no longer shows `previous`, e.g. after other output or on the first frame
+    stale: bool,
+    // longer lines are cut short. a line the terminal wraps would push every row under it down
+    // and the diffs would land on the wrong cells
+    width: Option<usize>,
+    stats: RenderStats,
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        Screen {
+            stale: true,
+            ..Screen::default()
+        }
+    }
+
+    // repaint everything on the next frame
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    pub fn set_width(&mut self, width: usize) {
+        self.width = Some(width);
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    // shows `text` (lines separated by '\n') and flushes
+    pub fn draw(&mut self, out: &mut impl Write, text: &str) -> io::Result<()> {
+        let width = self.width.unwrap_or(usize::MAX);
+        let frame: Vec<Vec<char>> = text
+            .lines()
+            .map(|line| line.chars().take(width).collect())
+            .collect();
+        let text: String = frame
+            .iter()
+            .flat_map(|line| line.iter().copied().chain(['\n']))
+            .collect();
+        let output = if self.stale {
+            self.stats.full_redraws += 1;
+            full_redraw(&text)
+        } else {
+            diff(&self.previous, &frame)
+        };
+
+        self.stats.frames += 1;
+        self.stats.bytes += output.len() as u64;
+        self.stats.full_bytes += full_redraw(&text).len() as u64;
+
+        self.previous = frame;
+        self.stale = false;
+
+        out.write_all(output.as_bytes())?;
+        out.flush()
+    }
+}
+
+fn full_redraw(text: &str) -> String {
+    let mut output = String::from(CLEAR);
+    output.push_str(text);
+    if !text.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+// escape sequences that turn `old` into `new` on a terminal currently showing `old`
+fn diff(old: &[Vec<char>], new: &[Vec<char>]) -> String {
+    let mut output = String::new();
+
+    for row in 0..old.len().max(new.len()) {
+        let old_line = old.get(row).map(Vec::as_slice).unwrap_or_default();
+        let new_line = new.get(row).map(Vec::as_slice).unwrap_or_default();
+
+        let mut col = 0;
+        while col < new_line.len() {
+            if old_line.get(col) == Some(&new_line[col]) {
+                col += 1;
+                continue;
+            }
+
+            // extend the run over every change that follows closely enough
+            let start = col;
+            let mut end = col + 1;
+            let mut scan = end;
+            while scan < new_line.len() && scan - end < MAX_SKIPPED_CELLS {
+                if old_line.get(scan) != Some(&new_line[scan]) {
+                    end = scan + 1;
+                }
+                scan += 1;
+            }
+
+            move_to(&mut output, row, start);
+            output.extend(&new_line[start..end]);
+            col = end;
+        }
+
+        if old_line.len() > new_line.len() {
+            move_to(&mut output, row, new_line.len());
+            output.push_str("\x1B[K");
+        }
+    }
+
+    // leave the cursor under the frame, where anything printed afterwards belongs
+    if !output.is_empty() {
+        move_to(&mut output, new.len(), 0);
+    }
+    output
+}
+
+// rows and columns count from 0 here and from 1 on the terminal
+fn move_to(output: &mut String, row: usize, col: usize) {
+    output.push_str(&format!("\x1B[{};{}H", row + 1, col + 1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drawn(screen: &mut Screen, text: &str) -> String {
+        let mut out = Vec::new();
+        screen
+            .draw(&mut out, text)
+            .expect("a Vec takes every write");
+        String::from_utf8(out).expect("frames are utf-8")
+    }
+
+    #[test]
+    fn the_first_frame_is_drawn_in_full() {
+        let mut screen = Screen::new();
+        assert_eq!(drawn(&mut screen, "ab\ncd"), "\x1B[2J\x1B[Hab\ncd\n");
+        assert_eq!(screen.stats().full_redraws, 1);
+    }
+
+    #[test]
+    fn later_frames_only_send_the_changed_cells() {
+        let mut screen = Screen::new();
+        drawn(&mut screen, "abcdef\nghijkl\n");
+        assert_eq!(
+            drawn(&mut screen, "abcdef\nghiXkl\n"),
+            "\x1B[2;4HX\x1B[3;1H"
+        );
+        assert_eq!(drawn(&mut screen, "abcdef\nghiXkl\n"), "");
+    }
+
+    #[test]
+    fn close_changes_are_sent_as_one_run() {
+        let mut screen = Screen::new();
+        drawn(&mut screen, "..........");
+        assert_eq!(drawn(&mut screen, ".X..X....."), "\x1B[1;2HX..X\x1B[2;1H");
+    }
+
+    #[test]
+    fn a_shorter_line_clears_what_is_left_of_the_old_one() {
+        let mut screen = Screen::new();
+        drawn(&mut screen, "abcdef\nxyz");
+        assert_eq!(drawn(&mut screen, "abc\nxyz"), "\x1B[1;4H\x1B[K\x1B[3;1H");
+    }
+
+    #[test]
+    fn lines_wider_than_the_screen_are_cut_short() {
+        let mut screen = Screen::new();
+        screen.set_width(4);
+        assert_eq!(
+            drawn(&mut screen, "abcd\nefghijkl\nmn"),
+            "\x1B[2J\x1B[Habcd\nefgh\nmn\n"
+        );
+        // the row under the long line is still addressed where the terminal shows it
+        assert_eq!(
+            drawn(&mut screen, "abcd\nefghXjkl\nmX"),
+            "\x1B[3;2HX\x1B[4;1H"
+        );
+    }
+
+    #[test]
+    fn an_invalidated_screen_is_redrawn_in_full() {
+        let mut screen = Screen::new();
+        drawn(&mut screen, "abc");
+        screen.invalidate();
+        assert!(drawn(&mut screen, "abc").starts_with(CLEAR));
+        assert_eq!(screen.stats().frames, 2);
+        assert_eq!(screen.stats().full_redraws, 2);
+    }
+}
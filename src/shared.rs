@@ -0,0 +1,78 @@
+use libc::{MAP_ANONYMOUS, MAP_FAILED, MAP_SHARED, PROT_READ, PROT_WRITE, c_void, mmap, munmap};
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+// a value in an anonymous MAP_SHARED mapping, seen and changed alike by every process forked
+// after it was made. whatever goes in must not own heap memory: the heap is copied on fork, so
+// anything behind a pointer into it would quietly stop being shared
+pub struct Shared<T: ?Sized> {
+    ptr: NonNull<T>,
+}
+
+// like a Box, it owns what it points at
+unsafe impl<T: ?Sized + Send> Send for Shared<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for Shared<T> {}
+
+// page aligned, which is more than anything put in here needs. mmap refuses empty mappings
+fn map(bytes: usize) -> io::Result<NonNull<c_void>> {
+    let ptr = unsafe {
+        mmap(
+            std::ptr::null_mut(),
+            bytes.max(1),
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED | MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    if ptr == MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(NonNull::new(ptr).expect("mmap does not hand out null"))
+}
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> io::Result<Shared<T>> {
+        let ptr = map(size_of::<T>())?.cast::<T>();
+        unsafe { ptr.write(value) };
+        Ok(Shared { ptr })
+    }
+}
+
+impl<T: Copy> Shared<[T]> {
+    // `len` copies of `value`
+    pub fn filled(value: T, len: usize) -> io::Result<Shared<[T]>> {
+        let first = map(len * size_of::<T>())?.cast::<T>();
+        for n in 0..len {
+            unsafe { first.add(n).write(value) };
+        }
+        Ok(Shared {
+            ptr: NonNull::slice_from_raw_parts(first, len),
+        })
+    }
+}
+
+impl<T: ?Sized> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for Shared<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: ?Sized> Drop for Shared<T> {
+    fn drop(&mut self) {
+        let bytes = size_of_val(&**self);
+        unsafe {
+            self.ptr.drop_in_place();
+            munmap(self.ptr.as_ptr().cast(), bytes.max(1));
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::config::Config;
+use crate::controller::{ControllerKind, build_controllers, decide};
+use crate::court::Court;
+use crate::game::{Event, GameState, PointReason, Side, step};
+use crate::observer::{Observer, notify};
+use crate::skill::Skill;
+
+// rally lengths at or above this share the last histogram row
+const RALLY_BUCKETS: usize = 10;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BatchStats {
+    pub seed: u64,
+    pub skills: [Skill; 2],
+    pub court: Court,
+    pub matches: u32,
+    pub wins: [u32; 2],
+    pub frames: u64,
+    pub points: u32,
+    // points started on each side's serve, and how many of those the server won
+    pub serves: [u32; 2],
+    pub holds: [u32; 2],
+    pub rally_histogram: [u32; RALLY_BUCKETS],
+    pub total_rally_hits: u64,
+    pub longest_rally: u32,
+    pub misses: u32,
+    pub outs: u32,
+    pub net_cords: u32,
+    pub lets: u32,
+    pub faults: u32,
+}
+
+// the seed of match `n` in a batch started from `seed`. the first match is the one play --seed
+// plays, the rest take a splitmix64 step so batches from neighbouring base seeds share no matches
+pub fn match_seed(seed: u64, n: u64) -> u64 {
+    if n == 0 {
+        return seed;
+    }
+    let mut z = seed.wrapping_add(n.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// plays `matches` full games headless, match n on match_seed(seed, n). every event also goes to
+// `observers`
+pub fn run_batch(
+    matches: u32,
+    seed: u64,
+    config: &Config,
+    controllers: &[ControllerKind; 2],
+    skills: [Skill; 2],
+    court: Court,
+    observers: &mut [Box<dyn Observer>],
+) -> BatchStats {
+    let mut stats = BatchStats {
+        seed,
+        skills,
+        court,
+        ..BatchStats::default()
+    };
+
+    for n in 0..matches {
+        let mut game = config.new_game(match_seed(seed, n as u64), court);
+        game.skills = skills;
+        play_match(&mut game, controllers, &mut stats, observers);
+    }
+
+    stats
+}
+
+fn play_match(
+    game: &mut GameState,
+    kinds: &[ControllerKind; 2],
+    stats: &mut BatchStats,
+    observers: &mut [Box<dyn Observer>],
+) {
+    let mut controllers = build_controllers(kinds, game);
+    let mut server = game.serving_player;
+
+    while !game.game_over {
+        stats.frames += 1;
+
+        let inputs = decide(&mut controllers, game);
+        let events = step(game, &inputs);
+        notify(observers, game, &events);
+        for event in events {
+            match event {
+                Event::Serve(side) => server = side,
+                Event::NetCord => stats.net_cords += 1,
+                Event::Let(_) => stats.lets += 1,
+                Event::PointScored { winner, reason } => {
+                    stats.points += 1;
+                    stats.serves[server.index()] += 1;
+                    if winner == server {
+                        stats.holds[server.index()] += 1;
+                    }
+
+                    let rally = game.rally_length;
+                    stats.rally_histogram[(rally as usize).min(RALLY_BUCKETS - 1)] += 1;
+                    stats.total_rally_hits += rally as u64;
+                    stats.longest_rally = stats.longest_rally.max(rally);
+
+                    match reason {
+                        PointReason::Miss(_) => stats.misses += 1,
+                        PointReason::Out(_) => stats.outs += 1,
+                        PointReason::Fault(_) => stats.faults += 1,
+                    }
+                }
+                Event::GameOver { winner } => {
+                    stats.matches += 1;
+                    stats.wins[winner.index()] += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn percent(part: u32, whole: u32) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+impl fmt::Display for BatchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "simulated {} matches (seed {}, {} court, {} frames)",
+            self.matches, self.seed, self.court, self.frames
+        )?;
+
+        for side in [Side::Ping, Side::Pong] {
+            let i = side.index();
+            writeln!(
+                f,
+                "{} ({}): {} wins ({:.1}%)  serve held {}/{} ({:.1}%)",
+                side,
+                self.skills[i],
+                self.wins[i],
+                percent(self.wins[i], self.matches),
+                self.holds[i],
+                self.serves[i],
+                percent(self.holds[i], self.serves[i])
+            )?;
+        }
+
+        let held = self.holds[0] + self.holds[1];
+        writeln!(
+            f,
+            "points: {}  serve held overall: {:.1}%",
+            self.points,
+            percent(held, self.points)
+        )?;
+
+        let mean_rally = if self.points == 0 {
+            0.0
+        } else {
+            self.total_rally_hits as f64 / self.points as f64
+        };
+        writeln!(
+            f,
+            "rally length: mean {:.2}  longest {}",
+            mean_rally, self.longest_rally
+        )?;
+
+        let widest = self
+            .rally_histogram
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        for (hits, &count) in self.rally_histogram.iter().enumerate() {
+            let label = if hits == RALLY_BUCKETS - 1 {
+                format!("{}+", hits)
+            } else {
+                hits.to_string()
+            };
+            let bar = "█".repeat((count as usize * 40).div_ceil(widest as usize));
+            writeln!(
+                f,
+                "  {:>3} │ {:<40} {} ({:.1}%)",
+                label,
+                bar,
+                count,
+                percent(count, self.points)
+            )?;
+        }
+
+        let ratio = if self.outs == 0 {
+            "n/a".to_string()
+        } else {
+            format!("{:.2}", self.misses as f64 / self.outs as f64)
+        };
+        writeln!(
+            f,
+            "misses: {}  outs: {}  miss/out ratio: {}",
+            self.misses, self.outs, ratio
+        )?;
+
+        let per_match = if self.matches == 0 {
+            0.0
+        } else {
+            self.net_cords as f64 / self.matches as f64
+        };
+        writeln!(
+            f,
+            "net cords: {} ({:.3} per point, {:.2} per match)",
+            self.net_cords,
+            percent(self.net_cords, self.points) / 100.0,
+            per_match
+        )?;
+
+        let serves = self.serves[0] + self.serves[1] + self.lets;
+        write!(
+            f,
+            "lets: {}  service faults: {} ({:.1}% of serves)",
+            self.lets,
+            self.faults,
+            percent(self.faults, serves)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn batches_from_neighbouring_seeds_share_no_matches() {
+        let mut seen = HashSet::new();
+        for seed in 0..50 {
+            for n in 0..100 {
+                assert!(
+                    seen.insert(match_seed(seed, n)),
+                    "seed {} match {}",
+                    seed,
+                    n
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_batch_opens_with_the_match_play_would_play() {
+        assert_eq!(match_seed(42, 0), 42);
+        assert_ne!(match_seed(42, 1), 43);
+    }
+}
@@ -0,0 +1,109 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::physics::PADDLE_SPEED;
+use crate::{DIFFICULTY_SCALING, MISS_PROBABILITY_BASE};
+
+// how good a player is, whatever strategy drives their paddle
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Skill {
+    pub name: &'static str,
+    // rows the paddle can move per frame
+    pub paddle_speed: f64,
+    // ai players aim up to this many rows off the ball
+    pub target_noise: f64,
+    // chance of fluffing a ball that reaches the paddle, before speed is counted
+    pub miss_base: f64,
+    // extra miss chance per unit of ball speed
+    pub difficulty_scaling: f64,
+}
+
+pub const BEGINNER: Skill = Skill {
+    name: "beginner",
+    paddle_speed: 0.3,
+    target_noise: 2.5,
+    miss_base: 0.25,
+    difficulty_scaling: 0.1,
+};
+
+// the tuning the game always had
+pub const CLUB: Skill = Skill {
+    name: "club",
+    paddle_speed: PADDLE_SPEED,
+    target_noise: 1.0,
+    miss_base: MISS_PROBABILITY_BASE,
+    difficulty_scaling: DIFFICULTY_SCALING,
+};
+
+pub const PRO: Skill = Skill {
+    name: "pro",
+    paddle_speed: 0.75,
+    target_noise: 0.5,
+    miss_base: 0.03,
+    difficulty_scaling: 0.02,
+};
+
+pub const UNBEATABLE: Skill = Skill {
+    name: "unbeatable",
+    paddle_speed: 1.5,
+    target_noise: 0.0,
+    miss_base: 0.0,
+    difficulty_scaling: 0.0,
+};
+
+pub const SKILLS: [Skill; 4] = [BEGINNER, CLUB, PRO, UNBEATABLE];
+
+impl Skill {
+    pub fn from_name(name: &str) -> Option<Skill> {
+        SKILLS.into_iter().find(|skill| skill.name == name)
+    }
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        CLUB
+    }
+}
+
+impl fmt::Display for Skill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::controller::ControllerKind;
+    use crate::court::Court;
+    use crate::game::Side;
+    use crate::sim::run_batch;
+
+    #[test]
+    fn the_weaker_side_loses_most_matches() {
+        let controllers = [ControllerKind::Classic, ControllerKind::Classic];
+        for skills in [[BEGINNER, PRO], [PRO, BEGINNER]] {
+            let stats = run_batch(
+                100,
+                1,
+                &Config::default(),
+                &controllers,
+                skills,
+                Court::default(),
+                &mut [],
+            );
+            let pro = if skills[0] == PRO {
+                Side::Ping
+            } else {
+                Side::Pong
+            };
+            assert!(
+                stats.wins[pro.index()] >= 90,
+                "the pro won {} of {}",
+                stats.wins[pro.index()],
+                stats.matches
+            );
+        }
+    }
+}
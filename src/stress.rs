@@ -0,0 +1,293 @@
+use libc::{SIGUSR1, getpid, kill};
+use rand::Rng;
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::controller::{ControllerKind, Controllers, build_controllers, decide};
+use crate::court::Court;
+use crate::game::{GameState, Side, step};
+use crate::handoff::{Handoff, Transport, block_usr1, take_usr1};
+use crate::sim::match_seed;
+
+// this long without a turn played means both players are waiting for one nobody will give
+const STALL: Duration = Duration::from_secs(2);
+const WATCH_INTERVAL: Duration = Duration::from_millis(10);
+// the longest gap between two stray signals
+const STRAY_GAP_US: u64 = 200;
+
+// what the players keep track of as they take their turns
+struct Ledger {
+    played: AtomicU64,
+    turns: [AtomicU64; 2],
+    duplicated: AtomicU64,
+    overlapping: AtomicU64,
+    done: AtomicBool,
+}
+
+// the match the players take turns at, like flip and flop at the app. a turn holds the lock
+// from start to end, so one finding it taken has started while the other was still playing
+struct Table {
+    seed: u64,
+    game: GameState,
+    controllers: Controllers,
+    matches: u64,
+    frames: u64,
+}
+
+impl Table {
+    fn new(seed: u64) -> Table {
+        let game = Config::default().new_game(seed, Court::default());
+        Table {
+            seed,
+            controllers: build_controllers(
+                &[ControllerKind::Classic, ControllerKind::Classic],
+                &game,
+            ),
+            game,
+            matches: 0,
+            frames: 0,
+        }
+    }
+
+    // steps the match until the turn leaves `side`. a decided match makes way for the next
+    fn play_turn(&mut self, side: Side) {
+        while self.game.turn == side {
+            let inputs = decide(&mut self.controllers, &self.game);
+            step(&mut self.game, &inputs);
+            self.frames += 1;
+            if self.game.game_over {
+                self.matches += 1;
+                let seed = match_seed(self.seed, self.matches);
+                self.game = Config::default().new_game(seed, self.game.court);
+                self.controllers = build_controllers(
+                    &[ControllerKind::Classic, ControllerKind::Classic],
+                    &self.game,
+                );
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StressReport {
+    // the --transport name, or process-wide
+    pub transport: String,
+    pub handoffs: u64,
+    pub seed: u64,
+    // turns played before the run finished or got stuck
+    pub played: u64,
+    pub turns: [u64; 2],
+    // frames stepped over those turns, and the matches they decided
+    pub frames: u64,
+    pub matches: u64,
+    // SIGUSR1 sent to the process meanwhile, for nobody in particular
+    pub stray_signals: u64,
+    // a side was woken while the game had the turn on the other side
+    pub duplicated: u64,
+    // a side started its turn while the other was still in its own
+    pub overlapping: u64,
+    // the run got stuck with both players waiting
+    pub lost: bool,
+    pub seconds: f64,
+}
+
+impl StressReport {
+    pub fn passed(&self) -> bool {
+        !self.lost && self.duplicated == 0 && self.overlapping == 0
+    }
+}
+
+impl fmt::Display for StressReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "stress: {} of {} handoffs over {} in {:.3}s, {} stray SIGUSR1 sent to the process (seed {})",
+            self.played, self.handoffs, self.transport, self.seconds, self.stray_signals, self.seed
+        )?;
+        writeln!(
+            f,
+            "turns played: ping {}  pong {}, {} frames over {} matches",
+            self.turns[0], self.turns[1], self.frames, self.matches
+        )?;
+        writeln!(
+            f,
+            "duplicated {}  overlapping {}  {}",
+            self.duplicated,
+            self.overlapping,
+            if self.lost {
+                "lost a turn, both players were left waiting"
+            } else {
+                "none lost"
+            }
+        )?;
+        write!(f, "{}", if self.passed() { "passed" } else { "FAILED" })
+    }
+}
+
+// the handoff of flip and flop without the trampoline: SIGUSR1 to the whole process, taken by
+// whichever waiting thread the kernel picks, and taken to mean it is that thread's turn.
+// the giver is waiting too by the time it lands, a stray signal wakes a player out of turn and
+// two pending at once merge into one
+struct ProcessWide;
+
+impl Handoff for ProcessWide {
+    fn give(&self, _to: Side) {
+        unsafe {
+            kill(getpid(), SIGUSR1);
+        }
+    }
+
+    fn wait(&self, _side: Side) {
+        take_usr1();
+    }
+}
+
+fn play(side: Side, handoff: &dyn Handoff, ledger: &Ledger, table: &Mutex<Table>, handoffs: u64) {
+    handoff.enter(side);
+    loop {
+        handoff.wait(side);
+        if ledger.done.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let mut table = match table.try_lock() {
+            Ok(table) => table,
+            Err(TryLockError::WouldBlock) => {
+                ledger.overlapping.fetch_add(1, Ordering::SeqCst);
+                table.lock().unwrap_or_else(PoisonError::into_inner)
+            }
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        };
+        // woken out of turn. the turn is passed on to whoever the game says has it, the way
+        // the process-wide handoff would have played it
+        if table.game.turn != side {
+            ledger.duplicated.fetch_add(1, Ordering::SeqCst);
+            let owner = table.game.turn;
+            drop(table);
+            handoff.give(owner);
+            continue;
+        }
+        table.play_turn(side);
+        ledger.turns[side.index()].fetch_add(1, Ordering::SeqCst);
+        drop(table);
+
+        let last = ledger.played.fetch_add(1, Ordering::SeqCst) + 1 >= handoffs;
+        if last {
+            ledger.done.store(true, Ordering::SeqCst);
+        }
+        handoff.give(side.opponent());
+        if last {
+            return;
+        }
+    }
+}
+
+// ping and pong play a match on `seed` for `handoffs` turns on threads of their own, over
+// `transport` or, without one, the process-wide way of flip and flop, while another thread
+// keeps sending SIGUSR1 to the process. every turn is checked against the game. SIGUSR1 stays
+// blocked in the calling thread afterwards
+pub fn stress(transport: Option<Transport>, handoffs: u64, seed: u64) -> io::Result<StressReport> {
+    if transport.is_some_and(|transport| !transport.threaded()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "only the threaded transports can be stressed",
+        ));
+    }
+    // the players and the stray signals' thread inherit this, so a stray signal waits for a
+    // player to take it instead of killing the process
+    block_usr1()?;
+    let handoff: Arc<dyn Handoff> = match transport {
+        Some(transport) => Arc::from(transport.create(Side::Ping, [|| {}; 2])?),
+        None => Arc::new(ProcessWide),
+    };
+    let ledger = Arc::new(Ledger {
+        played: AtomicU64::new(0),
+        turns: [AtomicU64::new(0), AtomicU64::new(0)],
+        duplicated: AtomicU64::new(0),
+        overlapping: AtomicU64::new(0),
+        done: AtomicBool::new(false),
+    });
+    let table = Arc::new(Mutex::new(Table::new(seed)));
+
+    let started = Instant::now();
+    let mut players = Vec::new();
+    for side in [Side::Ping, Side::Pong] {
+        let handoff = Arc::clone(&handoff);
+        let ledger = Arc::clone(&ledger);
+        let table = Arc::clone(&table);
+        players.push(
+            thread::Builder::new()
+                .name(side.to_string())
+                .spawn(move || play(side, &*handoff, &ledger, &table, handoffs))?,
+        );
+    }
+    let stray = {
+        let ledger = Arc::clone(&ledger);
+        thread::Builder::new()
+            .name("stray".to_string())
+            .spawn(move || {
+                let mut rng = rand::rng();
+                let mut sent = 0;
+                while !ledger.done.load(Ordering::SeqCst) {
+                    unsafe {
+                        kill(getpid(), SIGUSR1);
+                    }
+                    sent += 1;
+                    thread::sleep(Duration::from_micros(rng.random_range(0..STRAY_GAP_US)));
+                }
+                sent
+            })?
+    };
+
+    let mut progress = (0, Instant::now());
+    let lost = loop {
+        thread::sleep(WATCH_INTERVAL);
+        let played = ledger.played.load(Ordering::SeqCst);
+        if ledger.done.load(Ordering::SeqCst) {
+            break false;
+        }
+        if played != progress.0 {
+            progress = (played, Instant::now());
+        } else if progress.1.elapsed() >= STALL {
+            break true;
+        }
+    };
+    let seconds = started.elapsed().as_secs_f64();
+    // a stuck run has its players waiting for good, they are left behind
+    ledger.done.store(true, Ordering::SeqCst);
+    let stray_signals = stray.join().unwrap_or(0);
+    // the process-wide way can leave the player that did not finish waiting too
+    if !lost && transport.is_some() {
+        for player in players {
+            let _ = player.join();
+        }
+    }
+
+    // a player left waiting never took the lock, one stuck mid-turn has it for good
+    let (frames, matches) = match table.try_lock() {
+        Ok(table) => (table.frames, table.matches),
+        Err(_) => (0, 0),
+    };
+    Ok(StressReport {
+        transport: transport
+            .map_or("process-wide", Transport::name)
+            .to_string(),
+        handoffs,
+        seed,
+        played: ledger.played.load(Ordering::SeqCst),
+        turns: [0, 1].map(|side| ledger.turns[side].load(Ordering::SeqCst)),
+        frames,
+        matches,
+        stray_signals,
+        duplicated: ledger.duplicated.load(Ordering::SeqCst),
+        overlapping: ledger.overlapping.load(Ordering::SeqCst),
+        lost,
+        seconds,
+    })
+}
@@ -0,0 +1,262 @@
+use libc::{
+    _exit, ECHO, ICANON, POLLIN, SIGINT, SIGTERM, SIGWINCH, STDIN_FILENO, STDOUT_FILENO, TCSANOW,
+    TIOCGWINSZ, VMIN, VTIME, c_int, ioctl, poll, pollfd, read, sigaction, sigemptyset, tcgetattr,
+    tcsetattr, termios, winsize, write,
+};
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+const ENTER_ALTERNATE_SCREEN: &str = "\x1B[?1049h\x1B[?25l";
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1B[?25h\x1B[?1049l";
+// how long the rest of an escape sequence gets to arrive before a lone ESC counts as escape
+const ESCAPE_WAIT_MS: i32 = 25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Escape,
+    Backspace,
+}
+
+// puts stdin into non-canonical, no-echo mode until dropped.
+// output processing and ISIG stay on so '\n' still returns the carriage and ctrl-c still works
+pub struct RawMode {
+    original: termios,
+}
+
+// the settings from before the first RawMode, for exit paths that never run destructors
+static ORIGINAL: OnceLock<termios> = OnceLock::new();
+
+impl RawMode {
+    pub fn enable() -> io::Result<RawMode> {
+        let mut original: termios = unsafe { std::mem::zeroed() };
+        if unsafe { tcgetattr(STDIN_FILENO, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        ORIGINAL.get_or_init(|| original);
+
+        let mut raw = original;
+        raw.c_lflag &= !(ICANON | ECHO);
+        raw.c_cc[VMIN] = 0;
+        raw.c_cc[VTIME] = 0;
+        if unsafe { tcsetattr(STDIN_FILENO, TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(RawMode { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            tcsetattr(STDIN_FILENO, TCSANOW, &self.original);
+        }
+    }
+}
+
+// draws on the terminal's second buffer with the cursor hidden until dropped,
+// so the shell's scrollback comes back untouched
+pub struct AlternateScreen;
+
+static ON_ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+
+impl AlternateScreen {
+    pub fn enter() -> io::Result<AlternateScreen> {
+        // set first, a quit signal between the two must still find its way back
+        ON_ALTERNATE_SCREEN.store(true, Ordering::SeqCst);
+        let mut out = io::stdout().lock();
+        out.write_all(ENTER_ALTERNATE_SCREEN.as_bytes())?;
+        out.flush()?;
+        Ok(AlternateScreen)
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        leave_alternate_screen();
+    }
+}
+
+fn leave_alternate_screen() {
+    if ON_ALTERNATE_SCREEN.swap(false, Ordering::SeqCst) {
+        unsafe {
+            write(
+                STDOUT_FILENO,
+                LEAVE_ALTERNATE_SCREEN.as_ptr().cast(),
+                LEAVE_ALTERNATE_SCREEN.len(),
+            );
+        }
+    }
+}
+
+// puts the terminal back the way it was before raw mode and the alternate screen.
+// only calls tcsetattr and write, so it is safe to use from a signal handler
+pub fn restore() {
+    if let Some(original) = ORIGINAL.get() {
+        unsafe {
+            tcsetattr(STDIN_FILENO, TCSANOW, original);
+        }
+    }
+    leave_alternate_screen();
+}
+
+// runs `handler` on ctrl-c and kill. installed before raw mode or the alternate screen, so no
+// moment is left where a quit signal finds the terminal changed and nothing to put it back
+pub fn on_quit_signals(handler: unsafe extern "C" fn(c_int)) {
+    let mut act: sigaction = unsafe { std::mem::zeroed() };
+    act.sa_sigaction = handler as *const () as usize;
+    unsafe {
+        sigemptyset(&mut act.sa_mask);
+        sigaction(SIGINT, &act, std::ptr::null_mut());
+        sigaction(SIGTERM, &act, std::ptr::null_mut());
+    }
+}
+
+// a quit handler for anything with nothing else to say on the way out
+pub extern "C" fn restore_and_exit(sig: c_int) {
+    restore();
+    unsafe { _exit(128 + sig) };
+}
+
+// columns and rows of the terminal on stdout, if there is one
+pub fn terminal_size() -> Option<(usize, usize)> {
+    let mut size: winsize = unsafe { std::mem::zeroed() };
+    if unsafe { ioctl(STDOUT_FILENO, TIOCGWINSZ, &mut size) } != 0 || size.ws_col == 0 {
+        return None;
+    }
+    Some((size.ws_col as usize, size.ws_row as usize))
+}
+
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn note_resize(_sig: c_int) {
+    RESIZED.store(true, Ordering::SeqCst);
+}
+
+// starts noting SIGWINCH for take_resize. without SA_RESTART, so a resize also wakes read_keys
+pub fn watch_resize() {
+    let mut act: sigaction = unsafe { std::mem::zeroed() };
+    act.sa_sigaction = note_resize as *const () as usize;
+    unsafe {
+        sigemptyset(&mut act.sa_mask);
+        sigaction(SIGWINCH, &act, std::ptr::null_mut());
+    }
+}
+
+// whether the terminal changed size since the last call
+pub fn take_resize() -> bool {
+    RESIZED.swap(false, Ordering::SeqCst)
+}
+
+// waits up to `timeout` for input and returns every key that arrived
+pub fn read_keys(timeout: Duration) -> io::Result<Vec<Key>> {
+    let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+    if !stdin_ready(timeout_ms)? {
+        return Ok(Vec::new());
+    }
+
+    let mut buf = [0u8; 64];
+    let mut len = read_stdin(&mut buf)?;
+    // an arrow key can be split across reads, its ESC must not be taken for the escape key
+    while incomplete_escape(&buf[..len]) && len < buf.len() && stdin_ready(ESCAPE_WAIT_MS)? {
+        let more = read_stdin(&mut buf[len..])?;
+        if more == 0 {
+            break;
+        }
+        len += more;
+    }
+
+    Ok(parse_keys(&buf[..len]))
+}
+
+fn stdin_ready(timeout_ms: i32) -> io::Result<bool> {
+    let mut fds = pollfd {
+        fd: STDIN_FILENO,
+        events: POLLIN,
+        revents: 0,
+    };
+    let ready = unsafe { poll(&mut fds, 1, timeout_ms) };
+    if ready < 0 {
+        let err = io::Error::last_os_error();
+        // a signal landing mid-poll just means nothing was typed
+        return if err.kind() == io::ErrorKind::Interrupted {
+            Ok(false)
+        } else {
+            Err(err)
+        };
+    }
+    Ok(ready > 0)
+}
+
+fn read_stdin(buf: &mut [u8]) -> io::Result<usize> {
+    let n = unsafe { read(STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(n as usize)
+}
+
+// whether `bytes` stops part way into one of the sequences parse_keys knows
+fn incomplete_escape(bytes: &[u8]) -> bool {
+    matches!(bytes, [.., 0x1b] | [.., 0x1b, b'[' | b'O'])
+}
+
+fn parse_keys(mut bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+
+    while let Some(&byte) = bytes.first() {
+        let (key, len) = match bytes {
+            [0x1b, b'[', b'A', ..] | [0x1b, b'O', b'A', ..] => (Key::Up, 3),
+            [0x1b, b'[', b'B', ..] | [0x1b, b'O', b'B', ..] => (Key::Down, 3),
+            [0x1b, b'[', b'C', ..] | [0x1b, b'O', b'C', ..] => (Key::Right, 3),
+            [0x1b, b'[', b'D', ..] | [0x1b, b'O', b'D', ..] => (Key::Left, 3),
+            [0x1b, ..] => (Key::Escape, 1),
+            [b'\r' | b'\n', ..] => (Key::Enter, 1),
+            [0x7f | 0x08, ..] => (Key::Backspace, 1),
+            _ => (Key::Char(byte as char), 1),
+        };
+        keys.push(key);
+        bytes = &bytes[len..];
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrow_keys_and_plain_keys_are_told_apart() {
+        assert_eq!(
+            parse_keys(b"\x1b[Aw\x1bOD\r\x7f"),
+            [
+                Key::Up,
+                Key::Char('w'),
+                Key::Left,
+                Key::Enter,
+                Key::Backspace
+            ]
+        );
+        assert_eq!(parse_keys(b"\x1bq"), [Key::Escape, Key::Char('q')]);
+    }
+
+    #[test]
+    fn a_read_ending_part_way_into_an_arrow_key_waits_for_the_rest() {
+        assert!(incomplete_escape(b"\x1b"));
+        assert!(incomplete_escape(b"w\x1b["));
+        assert!(incomplete_escape(b"\x1bO"));
+        assert!(!incomplete_escape(b"\x1b[A"));
+        assert!(!incomplete_escape(b"w"));
+        assert!(!incomplete_escape(b""));
+    }
+}
@@ -0,0 +1,230 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::game::{Event, GameState};
+use crate::render::{describe_event, draw_board};
+use crate::replay::{Frame, Replay};
+use crate::screen::Screen;
+use crate::term::{
+    AlternateScreen, Key, RawMode, on_quit_signals, read_keys, restore_and_exit, take_resize,
+    watch_resize,
+};
+
+const SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+// point endings linger like they do in the live game
+const POINT_END_DELAY: u64 = 1000;
+
+struct Player<'a> {
+    replay: &'a Replay,
+    // index into replay.frames of the first frame of each point
+    point_starts: Vec<usize>,
+    // the recorded base frame delay in milliseconds
+    frame_delay: u64,
+    index: usize,
+    playing: bool,
+    speed: usize,
+    // digits typed before enter, for jumping to a point
+    pending: String,
+    board: GameState,
+    screen: Screen,
+}
+
+impl Player<'_> {
+    fn point_at(&self, index: usize) -> usize {
+        self.point_starts.partition_point(|&start| start <= index)
+    }
+
+    fn jump_to_point(&mut self, point: usize) {
+        let point = point.clamp(1, self.point_starts.len());
+        self.index = self.point_starts[point - 1];
+    }
+
+    fn frame_delay(&self) -> Duration {
+        let frame = &self.replay.frames[self.index];
+        let point_over = frame
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::PointScored { .. } | Event::Let(_)));
+        let base = if point_over {
+            POINT_END_DELAY
+        } else {
+            self.frame_delay
+        };
+        Duration::from_secs_f64(base as f64 / 1000.0 / SPEEDS[self.speed])
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let frame = &self.replay.frames[self.index];
+        frame.apply(&mut self.board);
+
+        let mut screen = draw_board(&self.board, frame.turn);
+        for message in frame.events.iter().filter_map(describe_event) {
+            screen.push_str(&message);
+            screen.push('\n');
+        }
+        screen.push_str(&format!(
+            "replay │ frame {}/{} │ point {}/{} │ {}x │ {}\n",
+            self.index + 1,
+            self.replay.frames.len(),
+            self.point_at(self.index),
+            self.point_starts.len(),
+            SPEEDS[self.speed],
+            if self.playing { "playing" } else { "paused" },
+        ));
+        screen.push_str(
+            "[space] play/pause [←/→] step [+/-] speed [n/p] next/prev point [N enter] go to point [q] quit\n",
+        );
+        if !self.pending.is_empty() {
+            screen.push_str(&format!("go to point: {}\n", self.pending));
+        }
+
+        // the help line is wider than a small court, it is cut at the board's border
+        self.screen.set_width(self.board.court.width + 2);
+        self.screen.draw(out, &screen)
+    }
+
+    // returns false once the viewer should close
+    fn handle(&mut self, key: Key) -> bool {
+        let last = self.replay.frames.len() - 1;
+
+        match key {
+            Key::Char('q') | Key::Escape => return false,
+            Key::Char(' ') => {
+                if self.index == last {
+                    self.index = 0;
+                }
+                self.playing = !self.playing;
+            }
+            Key::Right | Key::Char('l') | Key::Char('.') => {
+                self.playing = false;
+                self.index = (self.index + 1).min(last);
+            }
+            Key::Left | Key::Char('h') | Key::Char(',') => {
+                self.playing = false;
+                self.index = self.index.saturating_sub(1);
+            }
+            Key::Char('+') | Key::Char('=') | Key::Up => {
+                self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+            }
+            Key::Char('-') | Key::Down => self.speed = self.speed.saturating_sub(1),
+            Key::Char('n') => self.jump_to_point(self.point_at(self.index) + 1),
+            Key::Char('p') => {
+                // like a music player: back to the start of this point, or the previous one if already there
+                let point = self.point_at(self.index);
+                if self.point_starts[point - 1] == self.index {
+                    self.jump_to_point(point.saturating_sub(1));
+                } else {
+                    self.jump_to_point(point);
+                }
+            }
+            Key::Char(digit) if digit.is_ascii_digit() => self.pending.push(digit),
+            Key::Backspace => {
+                self.pending.pop();
+            }
+            Key::Enter => {
+                if let Ok(point) = self.pending.parse() {
+                    self.jump_to_point(point);
+                }
+                self.pending.clear();
+            }
+            _ => {}
+        }
+
+        true
+    }
+}
+
+// the frame after each scored point starts the next one, unless it is the end of the match
+fn point_starts(frames: &[Frame]) -> Vec<usize> {
+    let mut starts = vec![0];
+    for (i, pair) in frames.windows(2).enumerate() {
+        let point_over = pair[0]
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::PointScored { .. }));
+        if point_over && !pair[1].game_over {
+            starts.push(i + 1);
+        }
+    }
+    starts
+}
+
+pub fn play(replay: &Replay) -> io::Result<()> {
+    if replay.frames.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "replay has no frames",
+        ));
+    }
+
+    let config = replay.config();
+    let mut player = Player {
+        replay,
+        point_starts: point_starts(&replay.frames),
+        frame_delay: config.timing.frame_delay,
+        index: 0,
+        playing: true,
+        speed: NORMAL_SPEED,
+        pending: String::new(),
+        board: config.new_game(replay.seed, replay.court),
+        screen: Screen::new(),
+    };
+
+    on_quit_signals(restore_and_exit);
+    let _raw = RawMode::enable()?;
+    let _alternate_screen = AlternateScreen::enter()?;
+    let mut out = io::stdout().lock();
+    // a replay keeps the court it was recorded on, a resize only needs a clean repaint
+    watch_resize();
+
+    loop {
+        if take_resize() {
+            player.screen.invalidate();
+        }
+        player.draw(&mut out)?;
+
+        let wait = if player.playing {
+            player.frame_delay()
+        } else {
+            Duration::from_secs(3600)
+        };
+        let keys = read_keys(wait)?;
+
+        if keys.is_empty() && player.playing {
+            if player.index + 1 < replay.frames.len() {
+                player.index += 1;
+            } else {
+                player.playing = false;
+            }
+        }
+
+        for key in keys {
+            if !player.handle(key) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Inputs, step};
+
+    #[test]
+    fn every_point_starts_once_and_the_end_of_the_match_is_not_one() {
+        let mut game = GameState::with_seed(8);
+        game.rules.points_to_win = 3;
+        let mut frames = vec![Frame::capture(0, &game, &[])];
+        while !game.game_over {
+            let events = step(&mut game, &Inputs::default());
+            frames.push(Frame::capture(frames.len() as u64, &game, &events));
+        }
+
+        let starts = point_starts(&frames);
+        let points = game.score.iter().sum::<u32>() as usize;
+        assert_eq!(starts.len(), points);
+        assert!(starts.iter().all(|&start| !frames[start].game_over));
+    }
+}